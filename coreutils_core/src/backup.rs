@@ -9,9 +9,10 @@
 //! - Existing
 //! - Simple
 //!
-//! **NOTE:** This module does not figure out default values based on environment
-//! variables as defined in the GNU backup options manual page. Whether to adhere to the
-//! GNU standard or not is up to the user of this module.
+//! **NOTE:** The default values based on environment variables, as defined in the GNU
+//! backup options manual page, are only used through [`backup_mode`] and
+//! [`backup_suffix`]. Whether to adhere to the GNU standard or not is up to the user of
+//! this module.
 //!
 //! ### None
 //! Can be specified by either supplying the strings `none` or `off`.
@@ -20,10 +21,11 @@
 //! ### Numbered
 //! Can be specified by either supplying the strings `numbered` or `t`.
 //! This mode always makes numbered backups. This means that a backup of a file `a.txt`
-//! will be backed up to `a.txt~X~` where `X` is the next number backup.
+//! will be backed up to `a.txt.~X~` where `X` is the next number backup.
 //!
 //! For example, if we create a file named `main.rs` and then back it up in this mode, we
-//! will get `main.rs~1~`. If we back the file up a second time, we will get `main.rs~2~`.
+//! will get `main.rs.~1~`. If we back the file up a second time, we will get
+//! `main.rs.~2~`.
 //!
 //! ### Simple
 //! Can be specified by either supplying the strings `simple` or `never` (not to be
@@ -41,12 +43,16 @@
 //! backups, it will continue to make simple backups.
 
 use std::{
+    env,
+    ffi::{CString, OsString},
     fs,
     io::{Error, ErrorKind},
+    os::unix::ffi::OsStrExt,
     path::{Path, PathBuf},
 };
 
-use regex::Regex;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use libc::{syscall, SYS_renameat2, AT_FDCWD, EINVAL, ENOSYS, RENAME_NOREPLACE};
 
 /// Convenience Enum to represent the different backup modes. See module documentation for
 /// an in-depth overview of what each backup mode means/does.
@@ -54,7 +60,7 @@ use regex::Regex;
 pub enum BackupMode {
     /// No backups will be made.
     None,
-    /// Backups will be made of the form `<filename>~<X>~` where `X` is the next backup
+    /// Backups will be made of the form `<filename>.~<X>~` where `X` is the next backup
     /// number.
     Numbered,
     /// The backup method will be consistant with what already exists for the current
@@ -89,11 +95,179 @@ impl From<&str> for BackupMode {
     }
 }
 
+/// Gets the [`BackupMode`] to use, from the `control` given by the user, or from the
+/// `VERSION_CONTROL` environment variable if there is none. Defaults to
+/// [`BackupMode::Existing`].
+#[inline]
+pub fn backup_mode(control: Option<&str>) -> BackupMode {
+    match control {
+        Some(control) => BackupMode::from(control),
+        None => match env::var("VERSION_CONTROL") {
+            Ok(control) if !control.is_empty() => BackupMode::from(control.as_str()),
+            _ => BackupMode::Existing,
+        },
+    }
+}
+
+/// Gets the suffix of simple backups, from the `suffix` given by the user, or from the
+/// `SIMPLE_BACKUP_SUFFIX` environment variable if there is none. Defaults to `~`.
+#[inline]
+pub fn backup_suffix(suffix: Option<&str>) -> String {
+    match suffix {
+        Some(suffix) => suffix.to_owned(),
+        None => match env::var("SIMPLE_BACKUP_SUFFIX") {
+            Ok(suffix) if !suffix.is_empty() => suffix,
+            _ => String::from("~"),
+        },
+    }
+}
+
+/// Computes the path of the backup of `target` for the backup `mode`, or `None` if no
+/// backup should be made.
+///
+/// Numbered backups are named `<target>.~<X>~`, where `X` is one more than the highest
+/// numbered backup of `target` found in its directory. Simple backups are named
+/// `<target><suffix>`.
+///
+/// # Errors
+/// If the directory of `target` cannot be read to find its numbered backups, an error variant
+/// will be returned.
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn backup_path(
+    target: &Path, mode: BackupMode, suffix: &str,
+) -> Result<Option<PathBuf>, Error> {
+    Ok(match mode {
+        BackupMode::None => None,
+        BackupMode::Numbered => {
+            Some(numbered_backup_path(target, last_numbered_backup(target)?.unwrap_or(0) + 1))
+        },
+        BackupMode::Existing => match last_numbered_backup(target)? {
+            Some(last) => Some(numbered_backup_path(target, last + 1)),
+            None => Some(simple_backup_path(target, suffix)),
+        },
+        BackupMode::Simple => Some(simple_backup_path(target, suffix)),
+    })
+}
+
+/// Path of the numbered backup `number` of `target`.
+fn numbered_backup_path(target: &Path, number: u64) -> PathBuf {
+    let mut name = OsString::from(target.as_os_str());
+    name.push(format!(".~{}~", number));
+    PathBuf::from(name)
+}
+
+/// Path of the simple backup of `target`.
+fn simple_backup_path(target: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(target.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Finds the number of the highest numbered backup of `target`, `None` if it has no numbered
+/// backups.
+///
+/// The names are compared as bytes, so that names that are not valid UTF-8 are found too.
+fn last_numbered_backup(target: &Path) -> Result<Option<u64>, Error> {
+    let file_name = match target.file_name() {
+        Some(file_name) => file_name.as_bytes(),
+        None => return Ok(None),
+    };
+    let parent = match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut last = None;
+    for entry in parent.read_dir()? {
+        let name = entry?.file_name();
+        let name = name.as_bytes();
+        let (prefix_len, suffix_len) = (file_name.len() + 2, 1);
+        if name.len() <= prefix_len + suffix_len
+            || !name.starts_with(file_name)
+            || !name[file_name.len()..].starts_with(b".~")
+            || !name.ends_with(b"~")
+        {
+            continue;
+        }
+
+        let number = &name[prefix_len..name.len() - suffix_len];
+        if number.iter().all(u8::is_ascii_digit) {
+            // Ok to unwrap: the number is only made of ASCII digits
+            last = last.max(std::str::from_utf8(number).unwrap().parse::<u64>().ok());
+        }
+    }
+    Ok(last)
+}
+
+/// Renames `file` to its numbered backup `new`, refusing to overwrite a backup that is
+/// already there.
+fn rename_to_numbered(file: &Path, new: &Path) -> Result<(), Error> {
+    match rename_no_replace(file, new) {
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("backup '{}' already exists", new.display()),
+        )),
+        res => res,
+    }
+}
+
+/// Renames `from` to `to` in a single step that fails if `to` exists, with `renameat2`.
+///
+/// Falls back to [`link_no_replace`] if the kernel or the file system doesn't support it.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn rename_no_replace(from: &Path, to: &Path) -> Result<(), Error> {
+    let c_from = CString::new(from.as_os_str().as_bytes())?;
+    let c_to = CString::new(to.as_os_str().as_bytes())?;
+    let res = unsafe {
+        syscall(
+            SYS_renameat2,
+            AT_FDCWD,
+            c_from.as_ptr(),
+            AT_FDCWD,
+            c_to.as_ptr(),
+            RENAME_NOREPLACE,
+        )
+    };
+    if res == 0 {
+        return Ok(());
+    }
+
+    let err = Error::last_os_error();
+    match err.raw_os_error() {
+        Some(EINVAL) | Some(ENOSYS) => link_no_replace(from, to),
+        _ => Err(err),
+    }
+}
+
+/// Renames `from` to `to`, failing if `to` exists, with [`link_no_replace`].
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+#[inline]
+fn rename_no_replace(from: &Path, to: &Path) -> Result<(), Error> {
+    link_no_replace(from, to)
+}
+
+/// Moves `from` to `to` by linking it there, which fails if `to` exists, and removing `from`.
+///
+/// A directory cannot be linked, so it is only renamed if nothing is at `to` just before.
+fn link_no_replace(from: &Path, to: &Path) -> Result<(), Error> {
+    if from.symlink_metadata()?.is_dir() {
+        if to.symlink_metadata().is_ok() {
+            return Err(Error::from(ErrorKind::AlreadyExists));
+        }
+        return fs::rename(from, to);
+    }
+
+    fs::hard_link(from, to)?;
+    fs::remove_file(from)
+}
+
 /// Creates a numbered backup. Does so by taking the input `file` and poking the parent
-/// directory to find a file of the form `<file>~<X>~` where `X` is a number. If none can
+/// directory to find a file of the form `<file>.~<X>~` where `X` is a number. If none can
 /// be found, a backup file is created where `X` is `1`. Else, it creates a backup file
 /// where `X` is `X + 1`.
 ///
+/// An existing backup is never overwritten.
+///
 /// # Errors
 /// If this function encounters any kind of I/O error, an error variant will be returned.
 // # Arguments
@@ -103,29 +277,14 @@ impl From<&str> for BackupMode {
 // `io::Error`
 #[inline]
 pub fn create_numbered_backup(file: &Path) -> Result<PathBuf, Error> {
-    let mut index = 1_u64;
-    loop {
-        if index == std::u64::MAX {
-            return Err(Error::new(
-                ErrorKind::AlreadyExists,
-                "Cannot create backup: too many backup files",
-            ));
-        }
-
-        let new = file.with_extension(format!("~{}~", index));
-        if !new.exists() {
-            match fs::rename(file, &new) {
-                Ok(()) => return Ok(new),
-                Err(err) => return Err(err),
-            };
-        }
-
-        index += 1;
-    }
+    // Ok to unwrap: there is always a path for numbered backups
+    let new = backup_path(file, BackupMode::Numbered, "")?.unwrap();
+    rename_to_numbered(file, &new)?;
+    Ok(new)
 }
 
 /// Creates a backup in-keeping with previous backups. Pokes the directory to see whether
-/// there are any numbered or simple backups of the input `file`. If numbered backups are
+/// there are any numbered backups of the input `file`. If numbered backups are
 /// found, a numbered backup will be created. Else, a simple backup is created using the
 /// input `suffix`
 ///
@@ -139,23 +298,14 @@ pub fn create_numbered_backup(file: &Path) -> Result<PathBuf, Error> {
 // `io::Error`
 #[inline]
 pub fn create_existing_backup(file: &Path, suffix: &str) -> Result<PathBuf, Error> {
-    let mut has_numbered_backup = false;
-    let regex = Regex::new(r"~\d+~").unwrap();
-    let parent = file.parent().unwrap();
-    for entry in parent.read_dir()?.flatten() {
-        if let Some(ext) = entry.path().extension() {
-            if regex.is_match(ext.to_str().unwrap()) {
-                has_numbered_backup = true;
-                break;
-            }
-        }
-    }
-
-    if has_numbered_backup {
-        create_numbered_backup(file)
+    // Ok to unwrap: there is always a path for existing backups
+    let new = backup_path(file, BackupMode::Existing, suffix)?.unwrap();
+    if new == simple_backup_path(file, suffix) {
+        fs::rename(file, &new)?;
     } else {
-        create_simple_backup(file, suffix)
+        rename_to_numbered(file, &new)?;
     }
+    Ok(new)
 }
 
 /// Creates a simple backup. Creates a backup of the form `<file><suffix>`. Overwrites any
@@ -178,3 +328,146 @@ pub fn create_simple_backup(file: &Path, suffix: &str) -> Result<PathBuf, Error>
         Err(error) => Err(error),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+
+    /// Creates a empty directory for a test, with a `file` in it.
+    fn test_dir(name: &str) -> (PathBuf, PathBuf) {
+        let dir = env::temp_dir().join(format!("coreutils-backup-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let file = dir.join("file.txt");
+        File::create(&file).unwrap();
+        (dir, file)
+    }
+
+    #[test]
+    fn simple_backup() {
+        let (dir, file) = test_dir("simple");
+        let path = |mode, suffix| backup_path(&file, mode, suffix).unwrap();
+
+        assert_eq!(None, path(BackupMode::None, "~"));
+        assert_eq!(Some(dir.join("file.txt~")), path(BackupMode::Simple, "~"));
+        assert_eq!(Some(dir.join("file.txt.bak")), path(BackupMode::Simple, ".bak"));
+        // Without numbered backups, existing backups are simple
+        assert_eq!(Some(dir.join("file.txt~")), path(BackupMode::Existing, "~"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn numbered_backup_from_scratch() {
+        let (dir, file) = test_dir("numbered");
+        // Backups of other files are not taken into account
+        File::create(dir.join("other.txt.~4~")).unwrap();
+
+        let path = backup_path(&file, BackupMode::Numbered, "~").unwrap();
+        assert_eq!(Some(dir.join("file.txt.~1~")), path);
+
+        let backup = create_numbered_backup(&file).unwrap();
+        assert_eq!(dir.join("file.txt.~1~"), backup);
+        assert!(backup.exists() && !file.exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn numbered_backup_continuing() {
+        let (dir, file) = test_dir("continuing");
+        File::create(dir.join("file.txt.~1~")).unwrap();
+        File::create(dir.join("file.txt.~9~")).unwrap();
+        File::create(dir.join("file.txt.~x~")).unwrap();
+
+        assert_eq!(
+            Some(dir.join("file.txt.~10~")),
+            backup_path(&file, BackupMode::Numbered, "~").unwrap()
+        );
+        // With numbered backups, existing backups are numbered
+        assert_eq!(
+            Some(dir.join("file.txt.~10~")),
+            backup_path(&file, BackupMode::Existing, "~").unwrap()
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn numbered_backup_of_non_utf8_name() {
+        use std::ffi::OsStr;
+
+        let (dir, _) = test_dir("non-utf8");
+        let file = dir.join(OsStr::from_bytes(b"caf\xe9"));
+        File::create(&file).unwrap();
+        File::create(dir.join(OsStr::from_bytes(b"caf\xe9.~1~"))).unwrap();
+
+        let backup = create_numbered_backup(&file).unwrap();
+        assert_eq!(dir.join(OsStr::from_bytes(b"caf\xe9.~2~")), backup);
+        assert!(dir.join(OsStr::from_bytes(b"caf\xe9.~1~")).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unreadable_directory_is_an_error() {
+        let (dir, _) = test_dir("unreadable");
+        let file = dir.join("missing-dir").join("file.txt");
+
+        assert!(backup_path(&file, BackupMode::Numbered, "~").is_err());
+        assert!(backup_path(&file, BackupMode::Existing, "~").is_err());
+        assert!(create_numbered_backup(&file).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn numbered_backup_is_never_overwritten() {
+        let (dir, file) = test_dir("no-overwrite");
+        let backup = dir.join("file.txt.~1~");
+        fs::write(&backup, "old backup").unwrap();
+
+        let err = rename_to_numbered(&file, &backup).unwrap_err();
+        assert_eq!(ErrorKind::AlreadyExists, err.kind());
+        assert!(file.exists());
+        assert_eq!("old backup", fs::read_to_string(&backup).unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn linked_backup_is_never_overwritten() {
+        let (dir, file) = test_dir("link-no-overwrite");
+        let backup = dir.join("file.txt.~1~");
+        fs::write(&backup, "old backup").unwrap();
+
+        let err = link_no_replace(&file, &backup).unwrap_err();
+        assert_eq!(ErrorKind::AlreadyExists, err.kind());
+        assert!(file.exists());
+        assert_eq!("old backup", fs::read_to_string(&backup).unwrap());
+
+        let new = dir.join("file.txt.~2~");
+        link_no_replace(&file, &new).unwrap();
+        assert!(!file.exists() && new.exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn backup_options_precedence() {
+        env::set_var("VERSION_CONTROL", "numbered");
+        env::set_var("SIMPLE_BACKUP_SUFFIX", ".orig");
+        assert_eq!(BackupMode::Numbered, backup_mode(None));
+        assert_eq!(BackupMode::Simple, backup_mode(Some("simple")));
+        assert_eq!(".orig", backup_suffix(None));
+        assert_eq!(".bak", backup_suffix(Some(".bak")));
+
+        env::remove_var("VERSION_CONTROL");
+        env::remove_var("SIMPLE_BACKUP_SUFFIX");
+        assert_eq!(BackupMode::Existing, backup_mode(None));
+        assert_eq!("~", backup_suffix(None));
+    }
+}
@@ -0,0 +1,62 @@
+//! Helpers to build the command line interface of the utilities.
+
+use clap::{App, AppSettings::ColoredHelp};
+
+/// Text shown at the end of the help of every utility.
+pub const HELP_FOOTER: &str = "Report bugs at <https://github.com/GrayJack/coreutils/issues>.";
+
+/// Create the [`App`] of the utility `name`, described by `about`, with the settings shared by
+/// all utilities.
+///
+/// The help and version are shown with `-?`/`--help` and `-V`/`--version`, the help is colored
+/// and ends with [`HELP_FOOTER`]. The utility adds its version, authors and arguments.
+///
+/// ## Example
+/// ```rust
+/// use coreutils_core::cli::base_app;
+///
+/// let app = base_app("true", "Do nothing, successfully.").version("0.1.0");
+/// ```
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn base_app<'a, 'b>(name: &str, about: &'b str) -> App<'a, 'b> {
+    App::new(name)
+        .about(about)
+        .help_message("Display help information.")
+        .version_message("Display version information.")
+        .help_short("?")
+        .settings(&[ColoredHelp])
+        .after_help(HELP_FOOTER)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ErrorKind;
+
+    use super::*;
+
+    /// Return the help of `app`, as shown by the help `flag`.
+    fn help_of(app: App, flag: &str) -> String {
+        let err = app.get_matches_from_safe(vec!["test", flag]).unwrap_err();
+        assert_eq!(ErrorKind::HelpDisplayed, err.kind);
+        err.message
+    }
+
+    #[test]
+    fn help_has_footer() {
+        let help = help_of(base_app("test", "Test utility."), "--help");
+        assert!(help.contains("Test utility."));
+        assert!(help.trim_end().ends_with(HELP_FOOTER));
+    }
+
+    #[test]
+    fn help_short() {
+        let help = help_of(base_app("test", "Test utility."), "-?");
+        assert!(help.contains("-?, --help       Display help information."));
+    }
+
+    #[test]
+    fn version_message() {
+        let help = help_of(base_app("test", "Test utility.").version("1.2.3"), "--help");
+        assert!(help.contains("-V, --version    Display version information."));
+    }
+}
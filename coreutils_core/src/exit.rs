@@ -0,0 +1,85 @@
+//! Exit statuses shared by the utilities, following the GNU conventions, and a helper to quit
+//! with an error message.
+
+use std::{env, fmt::Display, path::Path, process};
+
+/// The utility did what it was asked to.
+pub const SUCCESS: i32 = 0;
+/// A minor problem, like a file that could not be processed.
+pub const FAILURE: i32 = 1;
+/// A serious problem, like an invalid usage of the utility.
+pub const USAGE: i32 = 2;
+/// The utility itself failed, before or instead of running the command it was given.
+pub const CANCELED: i32 = 125;
+/// The command to run was found but could not be invoked.
+pub const CANNOT_INVOKE: i32 = 126;
+/// The command to run was not found.
+pub const NOT_FOUND: i32 = 127;
+
+/// Returns the name the utility was invoked with, without its directories.
+///
+/// It's the file name of the first argument of the process, or an empty string if there is
+/// none.
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn util_name() -> String {
+    env::args_os()
+        .next()
+        .as_ref()
+        .and_then(|arg0| Path::new(arg0).file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Formats `msg` as a error message of the utility `name`, like `touch: invalid date`.
+///
+/// ## Example
+/// ```rust
+/// use coreutils_core::exit::error_message;
+///
+/// assert_eq!("touch: invalid date", error_message("touch", "invalid date"));
+/// ```
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn error_message(name: &str, msg: impl Display) -> String {
+    format!("{}: {}", name, msg)
+}
+
+/// Prints `msg` to the standard error, prefixed with the name of the utility, and exits with
+/// `code`.
+///
+/// ## Example
+/// ```rust,no_run
+/// use coreutils_core::exit::{die, USAGE};
+///
+/// die(USAGE, "missing operand");
+/// ```
+pub fn die<M: Display>(code: i32, msg: M) -> ! {
+    eprintln!("{}", error_message(&util_name(), msg));
+    process::exit(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_format() {
+        assert_eq!("touch: invalid date", error_message("touch", "invalid date"));
+        assert_eq!(
+            "time: cannot run cmd: No such file",
+            error_message("time", format_args!("cannot run {}: {}", "cmd", "No such file"))
+        );
+    }
+
+    #[test]
+    fn util_name_has_no_directories() {
+        let name = util_name();
+
+        assert!(!name.is_empty());
+        assert!(!name.contains('/'));
+    }
+
+    #[test]
+    fn die_never_returns() {
+        let _: fn(i32, &'static str) -> ! = die::<&'static str>;
+    }
+}
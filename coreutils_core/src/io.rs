@@ -0,0 +1,126 @@
+//! Helpers to write raw bytes, that may not be valid UTF-8, like the names and passwords of the
+//! [`group`](crate::os::group) and [`passwd`](crate::os::passwd) modules.
+
+use std::{
+    io::{self, Write},
+    os::unix::io::RawFd,
+};
+
+use bstr::BStr;
+use libc::{STDERR_FILENO, STDOUT_FILENO};
+
+/// Writes the bytes of `b` to `out` as they are, without any conversion.
+///
+/// Unlike formatting `b` with `{}`, that replaces the invalid UTF-8 sequences with the
+/// replacement character, the exact bytes are written.
+///
+/// # Errors
+/// If writing to `out` fails, the error is returned.
+///
+/// ## Example
+/// ```rust
+/// use coreutils_core::{io::write_bytes, ByteSlice};
+///
+/// let mut out = Vec::new();
+/// write_bytes(&mut out, b"caf\xe9".as_bstr()).unwrap();
+///
+/// assert_eq!(b"caf\xe9", out.as_slice());
+/// ```
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn write_bytes(out: &mut impl Write, b: &BStr) -> io::Result<()> {
+    out.write_all(b)
+}
+
+/// A writer that writes straight to a file descriptor, without any buffering.
+///
+/// It doesn't own the file descriptor and never closes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FdWriter {
+    fd: RawFd,
+}
+
+impl FdWriter {
+    /// Creates a [`FdWriter`] that writes to `fd`.
+    #[inline]
+    pub const fn new(fd: RawFd) -> Self {
+        FdWriter { fd }
+    }
+
+    /// Creates a [`FdWriter`] that writes to the standard output.
+    #[inline]
+    pub const fn stdout() -> Self {
+        Self::new(STDOUT_FILENO)
+    }
+
+    /// Creates a [`FdWriter`] that writes to the standard error.
+    #[inline]
+    pub const fn stderr() -> Self {
+        Self::new(STDERR_FILENO)
+    }
+}
+
+impl Write for FdWriter {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        loop {
+            let res = unsafe { libc::write(self.fd, buf.as_ptr().cast(), buf.len()) };
+
+            if res >= 0 {
+                break Ok(res as usize);
+            }
+
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                break Err(err);
+            }
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs::{self, File},
+        os::unix::io::AsRawFd,
+        process,
+    };
+
+    use bstr::{BString, ByteSlice};
+
+    use super::*;
+
+    #[test]
+    fn write_invalid_utf8() {
+        let name = BString::from(&b"gr\xffoup\xc3"[..]);
+        let mut out = Vec::new();
+
+        write_bytes(&mut out, name.as_bstr()).unwrap();
+
+        assert_eq!(b"gr\xffoup\xc3", out.as_slice());
+    }
+
+    #[test]
+    fn write_invalid_utf8_to_fd() {
+        let path = env::temp_dir().join(format!("coreutils-io-{}", process::id()));
+        let file = File::create(&path).unwrap();
+
+        let mut writer = FdWriter::new(file.as_raw_fd());
+        write_bytes(&mut writer, b"gr\xffoup\n".as_bstr()).unwrap();
+        drop(file);
+
+        let written = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(b"gr\xffoup\n", written.as_slice());
+    }
+
+    #[test]
+    fn write_to_closed_fd_fails() {
+        assert!(FdWriter::new(-1).write_all(b"data").is_err());
+    }
+}
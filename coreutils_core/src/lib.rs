@@ -5,9 +5,19 @@ pub use time;
 
 // Agnostic Modules
 pub mod backup;
+#[cfg(feature = "cli")]
+pub mod cli;
 pub mod consts;
 pub mod env;
+pub mod exit;
 pub mod input;
+pub mod io;
 pub mod mkfifo;
 pub mod mktemp;
+pub mod mode;
 pub mod os;
+pub mod size;
+#[cfg(unix)]
+pub mod xattr;
+#[cfg(any(test, feature = "testutil"))]
+pub mod testutil;
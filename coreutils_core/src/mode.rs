@@ -0,0 +1,303 @@
+//! Helper functions to deal with file modes (type and permission bits).
+
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+/// Bit mask for the file type bits.
+const S_IFMT: u32 = 0o170_000;
+/// Socket file type.
+const S_IFSOCK: u32 = 0o140_000;
+/// Symbolic link file type.
+const S_IFLNK: u32 = 0o120_000;
+/// Regular file type.
+const S_IFREG: u32 = 0o100_000;
+/// Block device file type.
+const S_IFBLK: u32 = 0o060_000;
+/// Directory file type.
+const S_IFDIR: u32 = 0o040_000;
+/// Character device file type.
+const S_IFCHR: u32 = 0o020_000;
+/// FIFO file type.
+const S_IFIFO: u32 = 0o010_000;
+
+/// Set user ID on execution bit.
+const S_ISUID: u32 = 0o4000;
+/// Set group ID on execution bit.
+const S_ISGID: u32 = 0o2000;
+/// Sticky bit.
+const S_ISVTX: u32 = 0o1000;
+
+/// Bits of the user class: read, write, execute and set user ID.
+const USER_BITS: u32 = 0o4700;
+/// Bits of the group class: read, write, execute and set group ID.
+const GROUP_BITS: u32 = 0o2070;
+/// Bits of the others class: read, write, execute and sticky.
+const OTHER_BITS: u32 = 0o1007;
+/// All the permission bits.
+const ALL_BITS: u32 = 0o7777;
+
+/// Enum that holds the possible errors while parsing a mode with [`parse_mode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModeError {
+    /// Happens when a octal mode is greater than `7777`.
+    ///
+    /// It holds the invalid mode.
+    OctalOutOfRange(String),
+    /// Happens when a clause of a symbolic mode is not valid.
+    ///
+    /// It holds the invalid clause.
+    InvalidClause(String),
+}
+
+impl Display for ModeError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModeError::OctalOutOfRange(mode) => {
+                write!(f, "Invalid mode '{}': octal mode is greater than 7777", mode)
+            },
+            ModeError::InvalidClause(clause) => {
+                write!(f, "Invalid mode: '{}' is not a valid symbolic mode clause", clause)
+            },
+        }
+    }
+}
+
+impl StdError for ModeError {}
+
+/// Parses the `spec` of a mode as `chmod` does, and applies it to the `current` mode.
+///
+/// The `spec` can be a octal mode, like `755` or `0644`, that replaces all the permission bits,
+/// or a comma separated list of symbolic clauses, like `u+rwx,go-w`. Each clause is made of the
+/// classes it applies to (`u`, `g`, `o` or `a`, all of them if none), followed by one or more
+/// operations: a operator (`+` to add, `-` to remove or `=` to set the permissions) followed by
+/// the permissions (any of `rwxXst`) or a class to copy the permissions from (`u`, `g` or `o`).
+///
+/// `X` is the execute permission only if the file is a directory or already has some execute
+/// permission. The umask is not taken into account when no class is given, it is up to the
+/// caller to apply it.
+///
+/// The file type bits of `current` are kept as is.
+///
+/// # Errors
+/// If the `spec` is not a valid mode, an error variant will be returned.
+///
+/// ## Example
+/// ```rust
+/// use coreutils_core::mode::parse_mode;
+///
+/// assert_eq!(Ok(0o755), parse_mode("755", 0o644));
+/// assert_eq!(Ok(0o711), parse_mode("u+x,go=x", 0o644));
+/// ```
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn parse_mode(spec: &str, current: u32) -> Result<u32, ModeError> {
+    if !spec.is_empty() && spec.bytes().all(|b| (b'0'..=b'7').contains(&b)) {
+        return match u32::from_str_radix(spec, 8) {
+            Ok(mode) if mode <= ALL_BITS => Ok((current & S_IFMT) | mode),
+            _ => Err(ModeError::OctalOutOfRange(spec.to_owned())),
+        };
+    }
+
+    let mut mode = current;
+    for clause in spec.split(',') {
+        mode = apply_clause(clause, mode)
+            .ok_or_else(|| ModeError::InvalidClause(clause.to_owned()))?;
+    }
+    Ok(mode)
+}
+
+/// Applies a symbolic `clause` to `mode`, returning `None` if `clause` is not valid.
+fn apply_clause(clause: &str, mut mode: u32) -> Option<u32> {
+    let mut chars = clause.chars().peekable();
+
+    let mut who = 0;
+    while let Some(&c) = chars.peek() {
+        who |= match c {
+            'u' => USER_BITS,
+            'g' => GROUP_BITS,
+            'o' => OTHER_BITS,
+            'a' => ALL_BITS,
+            _ => break,
+        };
+        chars.next();
+    }
+    if who == 0 {
+        who = ALL_BITS;
+    }
+
+    // There must be at least one operation
+    chars.peek()?;
+    while let Some(op) = chars.next() {
+        if !matches!(op, '+' | '-' | '=') {
+            return None;
+        }
+
+        let mut perms = 0;
+        while let Some(&c) = chars.peek() {
+            perms |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                'X' if mode & S_IFMT == S_IFDIR || mode & 0o111 != 0 => 0o111,
+                'X' => 0,
+                's' => S_ISUID | S_ISGID,
+                't' => S_ISVTX,
+                // Copy the permissions of a class of the mode, to all the classes
+                'u' => ((mode >> 6) & 0o7) * 0o111,
+                'g' => ((mode >> 3) & 0o7) * 0o111,
+                'o' => (mode & 0o7) * 0o111,
+                _ => break,
+            };
+            chars.next();
+        }
+
+        let perms = perms & who;
+        mode = match op {
+            '+' => mode | perms,
+            '-' => mode & !perms,
+            _ => (mode & !who) | perms,
+        };
+    }
+
+    Some(mode)
+}
+
+/// Formats `mode` as the 10 characters string used by `ls -l`, like `-rwxr-xr-x`.
+///
+/// The first character is the file type: `-` for regular files, `d` for directories, `l` for
+/// symbolic links, `b` for block devices, `c` for character devices, `p` for FIFOs, `s` for
+/// sockets and `?` for unknown types.
+///
+/// The set user ID and set group ID bits are shown as `s` in place of the execute permission of
+/// the user and group, or `S` if that execute permission is not set. The sticky bit is shown the
+/// same way as `t` or `T` in place of the execute permission of others.
+///
+/// ## Example
+/// ```rust
+/// use coreutils_core::mode::format_mode;
+///
+/// assert_eq!("-rwxr-xr-x", format_mode(0o100_755));
+/// assert_eq!("drwxrwxrwt", format_mode(0o041_777));
+/// ```
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn format_mode(mode: u32) -> String {
+    let file_type = match mode & S_IFMT {
+        S_IFREG => '-',
+        S_IFDIR => 'd',
+        S_IFLNK => 'l',
+        S_IFBLK => 'b',
+        S_IFCHR => 'c',
+        S_IFIFO => 'p',
+        S_IFSOCK => 's',
+        _ => '?',
+    };
+
+    let mut out = String::with_capacity(10);
+    out.push(file_type);
+    out.push_str(&format_permissions(mode >> 6, mode & S_ISUID != 0, 's'));
+    out.push_str(&format_permissions(mode >> 3, mode & S_ISGID != 0, 's'));
+    out.push_str(&format_permissions(mode, mode & S_ISVTX != 0, 't'));
+    out
+}
+
+/// Formats the lower 3 bits of `bits` as `rwx`, with `special` shown in place of the execute
+/// permission if `special_set`, as lowercase if executable and as uppercase if not.
+fn format_permissions(bits: u32, special_set: bool, special: char) -> String {
+    let read = if bits & 0o4 != 0 { 'r' } else { '-' };
+    let write = if bits & 0o2 != 0 { 'w' } else { '-' };
+    let exec = match (bits & 0o1 != 0, special_set) {
+        (true, false) => 'x',
+        (false, false) => '-',
+        (true, true) => special,
+        (false, true) => special.to_ascii_uppercase(),
+    };
+
+    [read, write, exec].iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regular_file() {
+        assert_eq!("-rwxr-xr-x", format_mode(S_IFREG | 0o755));
+        assert_eq!("-rw-r--r--", format_mode(S_IFREG | 0o644));
+        assert_eq!("----------", format_mode(S_IFREG));
+    }
+
+    #[test]
+    fn setuid_and_setgid() {
+        assert_eq!("-rwsr-xr-x", format_mode(S_IFREG | 0o4755));
+        assert_eq!("-rwSr--r--", format_mode(S_IFREG | 0o4644));
+        assert_eq!("-rwxr-sr-x", format_mode(S_IFREG | 0o2755));
+        assert_eq!("-rwxr-Sr-x", format_mode(S_IFREG | 0o2745));
+    }
+
+    #[test]
+    fn sticky_directory() {
+        assert_eq!("drwxrwxrwt", format_mode(S_IFDIR | 0o1777));
+        assert_eq!("drwxrwxrwT", format_mode(S_IFDIR | 0o1776));
+    }
+
+    #[test]
+    fn file_types() {
+        assert_eq!("lrwxrwxrwx", format_mode(S_IFLNK | 0o777));
+        assert_eq!("brw-rw----", format_mode(S_IFBLK | 0o660));
+        assert_eq!("crw-rw-rw-", format_mode(S_IFCHR | 0o666));
+        assert_eq!("prw-r--r--", format_mode(S_IFIFO | 0o644));
+        assert_eq!("srwxr-xr-x", format_mode(S_IFSOCK | 0o755));
+        assert_eq!("?rwxr-xr-x", format_mode(0o755));
+    }
+
+    #[test]
+    fn parse_octal_mode() {
+        assert_eq!(Ok(S_IFREG | 0o755), parse_mode("755", S_IFREG | 0o600));
+        assert_eq!(Ok(S_IFREG | 0o644), parse_mode("0644", S_IFREG | 0o777));
+        assert_eq!(Ok(S_IFDIR | 0o1777), parse_mode("1777", S_IFDIR | 0o755));
+        assert_eq!(Err(ModeError::OctalOutOfRange("17777".to_owned())), parse_mode("17777", 0));
+    }
+
+    #[test]
+    fn parse_add_permissions() {
+        assert_eq!(Ok(0o744), parse_mode("u+rwx", 0o644));
+        assert_eq!(Ok(0o755), parse_mode("+x", 0o644));
+        assert_eq!(Ok(0o4755), parse_mode("u+s", 0o755));
+        assert_eq!(Ok(0o1777), parse_mode("o+t", 0o777));
+    }
+
+    #[test]
+    fn parse_remove_permissions() {
+        assert_eq!(Ok(0o755), parse_mode("go-w", 0o777));
+        assert_eq!(Ok(0o700), parse_mode("g-rx,o-rx", 0o755));
+        assert_eq!(Ok(0o755), parse_mode("u-s", 0o4755));
+    }
+
+    #[test]
+    fn parse_set_permissions() {
+        assert_eq!(Ok(0o444), parse_mode("a=r", 0o755));
+        assert_eq!(Ok(0o600), parse_mode("u=rw,go=", 0o755));
+        assert_eq!(Ok(0o775), parse_mode("g=u", 0o755));
+        assert_eq!(Ok(0o640), parse_mode("u=rw,g=r,o-rwx", 0o777));
+    }
+
+    #[test]
+    fn parse_conditional_execute() {
+        // Directories and files with some execute permission get it
+        assert_eq!(Ok(S_IFDIR | 0o755), parse_mode("a+X", S_IFDIR | 0o644));
+        assert_eq!(Ok(S_IFREG | 0o755), parse_mode("a+X", S_IFREG | 0o744));
+        // Files without any execute permission do not
+        assert_eq!(Ok(S_IFREG | 0o644), parse_mode("a+X", S_IFREG | 0o644));
+    }
+
+    #[test]
+    fn parse_invalid_mode() {
+        assert_eq!(Err(ModeError::InvalidClause("u".to_owned())), parse_mode("u", 0o644));
+        assert_eq!(Err(ModeError::InvalidClause("u+q".to_owned())), parse_mode("u+r,u+q", 0o644));
+        assert_eq!(Err(ModeError::InvalidClause("".to_owned())), parse_mode("", 0o644));
+        assert_eq!(Err(ModeError::InvalidClause("z+r".to_owned())), parse_mode("z+r", 0o644));
+        assert_eq!(Err(ModeError::InvalidClause("8".to_owned())), parse_mode("8", 0o644));
+    }
+}
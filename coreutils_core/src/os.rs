@@ -24,20 +24,96 @@ pub mod audit;
 #[cfg(target_os = "openbsd")]
 pub mod routing_table;
 
+use std::fmt;
+
 use libc::{
-    c_int, getegid, geteuid, getgid, getuid, gid_t, pid_t, suseconds_t, time_t, timeval, tm, uid_t,
+    c_char, c_int, getegid, geteuid, getgid, getuid, gid_t, pid_t, suseconds_t, time_t, timeval, tm, uid_t,
 };
 
 pub type Tm = tm;
 
+/// Size of the buffer past which the lookups in the user and group databases are not retried
+/// anymore.
+pub(crate) const MAX_BUFF_SIZE: usize = 16 << 20;
+
 /// Time stamp type used on system structures.
 pub type TimeVal = timeval;
 
-/// Group ID type.
-pub type Gid = gid_t;
+macro_rules! id_newtype {
+    ($(#[$attr:meta])* $name:ident($raw:ty)) => {
+        $(#[$attr])*
+        ///
+        /// It is a thin wrapper over the libc type, so IDs of different kinds are not mixed up.
+        /// Use [`From`] to create it from the libc type, and `as_raw` to get it back.
+        #[repr(transparent)]
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "serde", serde(transparent))]
+        pub struct $name($raw);
+
+        impl $name {
+            /// Returns the ID as the libc type.
+            #[inline]
+            pub const fn as_raw(self) -> $raw {
+                self.0
+            }
+        }
+
+        impl From<$raw> for $name {
+            #[inline]
+            fn from(id: $raw) -> Self {
+                $name(id)
+            }
+        }
+
+        impl From<$name> for $raw {
+            #[inline]
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl PartialEq<$raw> for $name {
+            #[inline]
+            fn eq(&self, other: &$raw) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl fmt::Display for $name {
+            #[inline]
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+    };
+}
+
+id_newtype! {
+    /// Group ID type.
+    ///
+    /// ## Example
+    /// Group and user IDs do not convert into each other
+    /// ```rust,compile_fail
+    /// use coreutils_core::os::{Gid, Uid};
+    ///
+    /// let uid: Uid = Gid::from(0);
+    /// ```
+    Gid(gid_t)
+}
 
-/// User ID type.
-pub type Uid = uid_t;
+id_newtype! {
+    /// User ID type.
+    ///
+    /// ## Example
+    /// User and group IDs do not convert into each other
+    /// ```rust,compile_fail
+    /// use coreutils_core::os::{Gid, Uid};
+    ///
+    /// let gid: Gid = Uid::from(0);
+    /// ```
+    Uid(uid_t)
+}
 
 /// Process ID Type.
 pub type Pid = pid_t;
@@ -53,24 +129,93 @@ pub type Susec = suseconds_t;
 
 /// Get the current running process user effective group id.
 #[inline]
-pub fn get_effective_gid() -> Uid {
-    unsafe { getegid() }
+pub fn get_effective_gid() -> Gid {
+    Gid::from(unsafe { getegid() })
 }
 
 /// Get the current running process user real group id.
 #[inline]
-pub fn get_real_gid() -> Uid {
-    unsafe { getgid() }
+pub fn get_real_gid() -> Gid {
+    Gid::from(unsafe { getgid() })
 }
 
 /// Get the current running process user effective user id.
 #[inline]
 pub fn get_effective_uid() -> Uid {
-    unsafe { geteuid() }
+    Uid::from(unsafe { geteuid() })
 }
 
 /// Get the current running process user real user id.
 #[inline]
 pub fn get_real_uid() -> Uid {
-    unsafe { getuid() }
+    Uid::from(unsafe { getuid() })
+}
+
+/// Calls `lookup` with `buff` until it doesn't fail with `ERANGE`, that means the buffer was
+/// too small, doubling the capacity of `buff` each time up to `max_size`.
+///
+/// It returns the error code of the last call, that is still `ERANGE` if the entry doesn't fit
+/// in `max_size` bytes.
+pub(crate) fn retry_on_erange(
+    buff: &mut Vec<c_char>, max_size: usize, mut lookup: impl FnMut(&mut Vec<c_char>) -> c_int,
+) -> c_int {
+    loop {
+        let error_flag = lookup(buff);
+        if error_flag != libc::ERANGE || buff.capacity() >= max_size {
+            break error_flag;
+        }
+
+        let new_size = (buff.capacity() * 2).min(max_size);
+        buff.reserve_exact(new_size - buff.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_grows_buffer_until_it_fits() {
+        let mut buff = Vec::with_capacity(16);
+        let mut sizes = Vec::new();
+
+        let error_flag = retry_on_erange(&mut buff, 1024, |buff| {
+            sizes.push(buff.capacity());
+            if buff.capacity() < 100 { libc::ERANGE } else { 0 }
+        });
+
+        assert_eq!(0, error_flag);
+        assert_eq!(4, sizes.len());
+        assert!(sizes.windows(2).all(|w| w[1] >= w[0] * 2));
+        assert!(buff.capacity() >= 100);
+    }
+
+    #[test]
+    fn retry_gives_up_at_max_size() {
+        let mut buff = Vec::with_capacity(16);
+        let mut calls = 0;
+
+        let error_flag = retry_on_erange(&mut buff, 100, |_| {
+            calls += 1;
+            libc::ERANGE
+        });
+
+        assert_eq!(libc::ERANGE, error_flag);
+        assert_eq!(4, calls);
+        assert!(buff.capacity() >= 100);
+    }
+
+    #[test]
+    fn retry_stops_on_other_errors() {
+        let mut buff = Vec::with_capacity(16);
+        let mut calls = 0;
+
+        let error_flag = retry_on_erange(&mut buff, 1024, |_| {
+            calls += 1;
+            libc::EIO
+        });
+
+        assert_eq!(libc::EIO, error_flag);
+        assert_eq!(1, calls);
+    }
 }
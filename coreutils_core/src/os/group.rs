@@ -1,38 +1,50 @@
 //! Module to deal more easily with UNIX groups.
 
-#[cfg(target_os = "macos")]
-use std::convert::TryInto;
-#[cfg(any(target_os = "solaris", target_os = "illumos"))]
-use std::os::raw::c_int;
 use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
     convert::TryFrom,
     error::Error as StdError,
     ffi::{CStr, CString, NulError},
     fmt::{self, Display},
+    hash::{Hash, Hasher},
     io::{self, Error as IoError},
+    iter,
     mem::MaybeUninit,
-    os::raw::c_char,
+    os::raw::{c_char, c_int},
     ptr,
     slice::Iter,
 };
 
 use bstr::{BStr, BString, ByteSlice};
-use libc::{getegid, getgrgid_r, getgrnam_r, getgroups, group};
+use libc::{
+    endgrent, getegid, getgrent, getgrgid_r, getgrnam_r, getgroups, gid_t, group, setgrent,
+};
 #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
-use libc::{getgrouplist, getpwnam_r};
+use libc::getgrouplist;
 #[cfg(any(target_os = "solaris", target_os = "illumos"))]
 use libc::{sysconf, _SC_NGROUPS_MAX};
 
 use self::Error::*;
-use super::{passwd::Error as PwError, Gid};
+#[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+use super::passwd::Passwd;
+use super::{passwd::Error as PwError, retry_on_erange, Gid, MAX_BUFF_SIZE};
 
 #[cfg(any(target_os = "solaris", target_os = "illumos"))]
 extern "C" {
     fn _getgroupsbymember(
-        username: *const c_char, glist: *mut Gid, maxids: c_int, numgids: c_int,
+        username: *const c_char, glist: *mut libc::gid_t, maxids: c_int, numgids: c_int,
     ) -> c_int;
 }
 
+/// Size of the buffer first given to [`getgrgid_r`] and [`getgrnam_r`].
+const BUFF_SIZE: usize = 16384; // Got this from manual page about `getgrgid_r`.
+/// Number of groups past which [`getgrouplist`] is not retried anymore, the most that Linux lets
+/// a user be in.
+#[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+const MAX_GROUPS: c_int = 65536;
+
 /// A iterator of group members.
 pub type Members = Vec<BString>;
 
@@ -55,6 +67,11 @@ pub enum Error {
     Passwd(Box<PwError>),
     /// Happens when creating a [`CString`] fails.
     Cstring(NulError),
+    /// Happens when a group name has a interior NUL byte, that would cut the name given to
+    /// [`getgrnam_r`].
+    ///
+    /// It holds the invalid name.
+    InvalidName(BString),
 }
 
 impl Display for Error {
@@ -72,6 +89,7 @@ impl Display for Error {
             GroupNotFound => write!(f, "Group was not found in the system"),
             Passwd(err) => write!(f, "Passwd error: {}", err),
             Cstring(err) => write!(f, "Failed to create CString: {}", err),
+            InvalidName(name) => write!(f, "Invalid group name {:?}: it has a NUL byte", name),
         }
     }
 }
@@ -111,7 +129,10 @@ impl From<Error> for IoError {
 /// This struct holds information about a group of UNIX/UNIX-like systems.
 ///
 /// Contains `sys/types.h` [`group`] struct attributes as Rust powefull types.
-#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Hash)]
+///
+/// Groups are compared, ordered and hashed by their ID only, as it is what identifies a group
+/// in the system. The name, password and members are not taken into account.
+#[derive(Clone, Debug)]
 pub struct Group {
     /// Group name.
     name: BString,
@@ -131,39 +152,7 @@ impl Group {
     /// into [`Group`], an error variant is returned.
     #[cfg_attr(feature = "inline-more", inline)]
     pub fn new() -> io::Result<Self> {
-        let mut gr = MaybeUninit::uninit();
-        let mut result = ptr::null_mut();
-        let buff_size = 16384; // Got this from manual page about `getgrgid_r`.
-        let mut buff = Vec::with_capacity(buff_size);
-
-        loop {
-            let error_flag = unsafe {
-                getgrgid_r(
-                    getegid(),
-                    gr.as_mut_ptr(),
-                    buff.as_mut_ptr(),
-                    buff.capacity(),
-                    &mut result,
-                )
-            };
-
-            if error_flag == 0 {
-                if result.is_null() {
-                    break Err(GroupNotFound.into());
-                } else {
-                    // Now that gr is initialized we get it
-                    let gr = unsafe { gr.assume_init() };
-
-                    break Ok(Group::try_from(gr)?);
-                }
-            } else if let Some(libc::ERANGE) = IoError::last_os_error().raw_os_error() {
-                // If there was a ERANGE error, that means the buffer was too small, so we add more
-                // `buff_size` each time we get that error
-                buff.reserve(buff_size);
-            } else {
-                break Err(IoError::last_os_error());
-            }
-        }
+        Self::from_gid(unsafe { getegid() })
     }
 
     /// Creates a [`Group`] using a `id` to get all attributes.
@@ -172,33 +161,31 @@ impl Group {
     /// If there is a error ocurrence when getting [`group`] (C struct) or converting it
     /// into [`Group`], an error variant is returned.
     #[cfg_attr(feature = "inline-more", inline)]
-    pub fn from_gid(id: Gid) -> io::Result<Self> {
+    pub fn from_gid(id: impl Into<Gid>) -> io::Result<Self> {
+        let id = id.into();
         let mut gr = MaybeUninit::uninit();
         let mut result = ptr::null_mut();
-        let buff_size = 16384; // Got this from manual page about `getgrgid_r`.
-        let mut buff = Vec::with_capacity(buff_size);
-
-        loop {
-            let error_flag = unsafe {
-                getgrgid_r(id, gr.as_mut_ptr(), buff.as_mut_ptr(), buff.capacity(), &mut result)
-            };
-
-            if error_flag == 0 {
-                if result.is_null() {
-                    break Err(GroupNotFound.into());
-                } else {
-                    // Now that gr is initialized we get it
-                    let gr = unsafe { gr.assume_init() };
-
-                    break Ok(Group::try_from(gr)?);
-                }
-            } else if let Some(libc::ERANGE) = IoError::last_os_error().raw_os_error() {
-                // If there was a ERANGE error, that means the buffer was too small, so we add more
-                // `buff_size` each time we get that error
-                buff.reserve(buff_size);
-            } else {
-                break Err(IoError::last_os_error());
-            }
+        let mut buff = Vec::with_capacity(BUFF_SIZE);
+
+        let error_flag = retry_on_erange(&mut buff, MAX_BUFF_SIZE, |buff| unsafe {
+            getgrgid_r(
+                id.as_raw(),
+                gr.as_mut_ptr(),
+                buff.as_mut_ptr(),
+                buff.capacity(),
+                &mut result,
+            )
+        });
+
+        match error_flag {
+            0 if result.is_null() => Err(GroupNotFound.into()),
+            0 => {
+                // Now that gr is initialized we get it
+                let gr = unsafe { gr.assume_init() };
+
+                Ok(Group::try_from(gr)?)
+            },
+            _ => Err(GetGroupFailed("getgrgid_r", error_flag).into()),
         }
     }
 
@@ -207,51 +194,112 @@ impl Group {
     /// # Errors
     /// If there is a error ocurrence when getting [`group`] (C struct) or converting it
     /// into [`Group`], an error variant is returned.
-    #[cfg_attr(feature = "inline-more", inline)]
+    #[inline]
     pub fn from_name(name: &str) -> io::Result<Self> {
+        Self::from_name_bytes(name.as_bytes().as_bstr())
+    }
+
+    /// Creates a [`Group`] using a `name`, that may not be valid UTF-8, to get all attributes.
+    ///
+    /// # Errors
+    /// If `name` has a NUL byte, a [`InvalidName`](Error::InvalidName) error is returned
+    /// instead of looking up a shorter name. If there is a error ocurrence when getting
+    /// [`group`] (C struct) or converting it into [`Group`], an error variant is returned.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn from_name_bytes(name: &BStr) -> io::Result<Self> {
         let mut gr = MaybeUninit::uninit();
         let mut result = ptr::null_mut();
-        let buff_size = 16384; // Got this from manual page about `getgrgid_r`.
-        let mut buff = Vec::with_capacity(buff_size);
-
-        let name = CString::new(name)?;
-
-        loop {
-            let error_flag = unsafe {
-                getgrnam_r(
-                    name.as_ptr() as *const c_char,
-                    gr.as_mut_ptr(),
-                    &mut buff[0],
-                    buff.len(),
-                    &mut result,
-                )
-            };
-
-            if error_flag == 0 {
-                if result.is_null() {
-                    break Err(GroupNotFound.into());
-                } else {
-                    // Now that gr is initialized we get it
-                    let gr = unsafe { gr.assume_init() };
-
-                    break Ok(Group::try_from(gr)?);
-                }
-            } else if let Some(libc::ERANGE) = IoError::last_os_error().raw_os_error() {
-                // If there was a ERANGE error, that means the buffer was too small, so we add more
-                // `buff_size` each time we get that error
-                buff.reserve(buff_size);
-            } else {
-                break Err(IoError::last_os_error());
-            }
+        let mut buff = Vec::with_capacity(BUFF_SIZE);
+
+        if name.contains(&0) {
+            return Err(InvalidName(name.to_owned()).into());
+        }
+        let name = CString::new(name.as_bytes())?;
+
+        let error_flag = retry_on_erange(&mut buff, MAX_BUFF_SIZE, |buff| unsafe {
+            getgrnam_r(
+                name.as_ptr() as *const c_char,
+                gr.as_mut_ptr(),
+                buff.as_mut_ptr(),
+                buff.capacity(),
+                &mut result,
+            )
+        });
+
+        match error_flag {
+            0 if result.is_null() => Err(GroupNotFound.into()),
+            0 => {
+                // Now that gr is initialized we get it
+                let gr = unsafe { gr.assume_init() };
+
+                Ok(Group::try_from(gr)?)
+            },
+            _ => Err(GetGroupFailed("getgrnam_r", error_flag).into()),
         }
     }
 
+    /// Creates a [`Group`] from a `spec` that is either a group name or a numeric group ID, as
+    /// `chgrp` accepts.
+    ///
+    /// The `spec` is looked up as a name first, and if there is no such group and it's all
+    /// digits, as a ID. A leading `+` forces the rest of `spec` to be taken as a ID, without
+    /// looking up any name.
+    ///
+    /// # Errors
+    /// If there is no group with the name or ID of `spec`, or if there is a error ocurrence
+    /// when getting [`group`] (C struct) or converting it into [`Group`], an error variant is
+    /// returned.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn resolve(spec: &BStr) -> io::Result<Self> {
+        if spec.first() == Some(&b'+') {
+            let id = &spec[1..];
+            return Self::from_gid(parse_gid(id.as_bstr()).ok_or(GroupNotFound)?);
+        }
+
+        match Self::from_name_bytes(spec) {
+            Err(err) if is_not_found(&err) => match parse_gid(spec) {
+                Some(id) => Self::from_gid(id),
+                None => Err(err),
+            },
+            by_name => by_name,
+        }
+    }
+
+    /// Check if a group named `name` exists.
+    #[inline]
+    pub fn exists_name(name: &BStr) -> bool {
+        Self::from_name_bytes(name).is_ok()
+    }
+
+    /// Check if a group with the `id` exists.
+    #[inline]
+    pub fn exists_gid(id: impl Into<Gid>) -> bool {
+        Self::from_gid(id).is_ok()
+    }
+
+    /// Check if the user `name` is listed as a member of the `Group`.
+    ///
+    /// Users having the `Group` as primary group are usually not listed as members.
+    #[inline]
+    pub fn is_member(&self, name: &BStr) -> bool {
+        self.mem.iter().any(|member| member == name)
+    }
+
     /// Returns the `Group` name.
     #[inline]
     pub fn name(&self) -> &BStr {
         self.name.as_bstr()
     }
 
+    /// Returns the `Group` name as UTF-8, with the invalid sequences replaced by the
+    /// replacement character `U+FFFD`.
+    ///
+    /// Use [`name`](Group::name) to get the exact bytes of the name.
+    #[inline]
+    pub fn name_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.name)
+    }
+
     /// Returns the `Group` id.
     #[inline]
     pub fn id(&self) -> Gid {
@@ -269,6 +317,21 @@ impl Group {
     pub fn mem(&self) -> &Members {
         &self.mem
     }
+
+    /// Returns the names of the `Group` members, which is empty for a group without members.
+    #[inline]
+    pub fn members(&self) -> &[BString] {
+        &self.mem
+    }
+
+    /// Returns the number of members of the `Group`.
+    ///
+    /// The members are copied out of [`group`] (C struct) when the `Group` is created, so this
+    /// doesn't allocate nor parse anything.
+    #[inline]
+    pub fn member_count(&self) -> usize {
+        self.mem.len()
+    }
 }
 
 impl TryFrom<group> for Group {
@@ -279,7 +342,7 @@ impl TryFrom<group> for Group {
         let name_ptr = gr.gr_name;
         let pw_ptr = gr.gr_passwd;
         let mut mem_list_ptr = gr.gr_mem;
-        let id = gr.gr_gid;
+        let id = Gid::from(gr.gr_gid);
 
         let name = if name_ptr.is_null() {
             return Err(NameCheckFailed);
@@ -296,28 +359,56 @@ impl TryFrom<group> for Group {
         };
 
         // Check if both `mem_ptr` and `*mem_ptr` are NULL since by "sys/types.h" definition
-        // group.gr_mem is of type `**c_char`
-        let mut mem_ptr = unsafe { *mem_list_ptr };
-        let mem = if !mem_list_ptr.is_null() && !mem_ptr.is_null() {
-            let mut members: Members = Members::new();
+        // group.gr_mem is of type `**c_char`. `mem_ptr` can only be read once `mem_list_ptr` is
+        // known not to be NULL
+        let mut mem = Members::new();
+        if !mem_list_ptr.is_null() {
+            let mut mem_ptr = unsafe { *mem_list_ptr };
 
-            while !mem_list_ptr.is_null() && !mem_ptr.is_null() {
+            while !mem_ptr.is_null() {
                 let mem_cstr = unsafe { CStr::from_ptr(mem_ptr) };
-                members.push(BString::from(mem_cstr.to_bytes()));
+                mem.push(BString::from(mem_cstr.to_bytes()));
 
                 // Update pointers
                 mem_list_ptr = unsafe { mem_list_ptr.add(1) };
                 mem_ptr = unsafe { *mem_list_ptr };
             }
-            members
-        } else {
-            Members::new()
-        };
+        }
 
         Ok(Group { name, id, passwd, mem })
     }
 }
 
+impl PartialEq for Group {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Group {}
+
+impl PartialOrd for Group {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Group {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl Hash for Group {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
 /// A collection of [`Group`].
 #[derive(Debug, Clone, Default)]
 pub struct Groups {
@@ -337,27 +428,10 @@ impl Groups {
     /// If it fails to get a [`Group`], an error variant will be returned.
     #[cfg_attr(feature = "inline-more", inline)]
     pub fn caller() -> io::Result<Self> {
-        // First we check if we indeed have groups.
-        // "If gidsetsize is 0 (fist parameter), getgroups() returns the number of supplementary
-        // group IDs associated with the calling process without modifying the array
-        // pointed to by grouplist."
-        let num_groups = unsafe { getgroups(0, ptr::null_mut()) };
-        if num_groups == -1 {
-            return Err(IoError::last_os_error());
-        }
-
-        let mut groups_ids = Vec::with_capacity(num_groups as usize);
-        let num_groups = unsafe { getgroups(num_groups, groups_ids.as_mut_ptr()) };
-        if num_groups == -1 {
-            return Err(IoError::last_os_error());
-        } else {
-            unsafe {
-                groups_ids.set_len(num_groups as usize);
-            }
-        }
+        let groups_ids = caller_gids()?;
 
         let groups = {
-            let mut gs = Vec::with_capacity(num_groups as usize);
+            let mut gs = Vec::with_capacity(groups_ids.len());
             for g_id in groups_ids {
                 if let Ok(gr) = Group::from_gid(g_id) {
                     gs.push(gr);
@@ -375,97 +449,54 @@ impl Groups {
     /// If it fails to get a [`Group`], an error variant will be returned.
     #[cfg_attr(feature = "inline-more", inline)]
     pub fn from_username(username: &str) -> io::Result<Self> {
-        let mut num_gr: i32 = 8;
-        let mut groups_ids = Vec::with_capacity(num_gr as usize);
-
-        let name = username.as_ptr() as *const c_char;
-
-        let mut res = 0;
         #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
-        unsafe {
-            let mut passwd = MaybeUninit::uninit();
-            let mut pw_result = ptr::null_mut();
-            let buff_size = 16384;
-            let mut buff = Vec::with_capacity(buff_size);
-
-            let passwd = loop {
-                let res_pwnam = getpwnam_r(
-                    name,
-                    passwd.as_mut_ptr(),
-                    buff.as_mut_ptr(),
-                    buff.capacity(),
-                    &mut pw_result,
-                );
-
-                if res_pwnam == 0 {
-                    if pw_result.is_null() {
-                        return Err(Passwd(Box::new(PwError::PasswdNotFound)).into());
-                    } else {
-                        break passwd.assume_init();
-                    }
-                } else if let Some(libc::ERANGE) = IoError::last_os_error().raw_os_error() {
-                    buff.reserve(buff_size);
-                } else {
-                    return Err(IoError::last_os_error());
-                }
-            };
-
-            let gid = passwd.pw_gid;
-
-            #[cfg(not(target_os = "macos"))]
-            if getgrouplist(name, gid, groups_ids.as_mut_ptr(), &mut num_gr) == -1 {
-                groups_ids.resize(num_gr as usize, 0);
-                res = getgrouplist(name, gid, groups_ids.as_mut_ptr(), &mut num_gr);
-            }
-
-            #[cfg(target_os = "macos")]
-            if getgrouplist(name, gid.try_into().unwrap(), groups_ids.as_mut_ptr(), &mut num_gr)
-                == -1
-            {
-                groups_ids.resize(num_gr as usize, 0);
-                res = getgrouplist(
-                    name,
-                    gid.try_into().unwrap(),
-                    groups_ids.as_mut_ptr(),
-                    &mut num_gr,
-                );
-            }
+        let groups_ids = {
+            let passwd = Passwd::from_name(username)?;
+            groups_of_user(username.as_bytes().as_bstr(), passwd.gid())?
+        };
 
-            groups_ids.set_len(num_gr as usize);
-        }
         #[cfg(any(target_os = "solaris", target_os = "illumos"))]
-        unsafe {
-            if _getgroupsbymember(name, groups_ids.as_mut_ptr(), num_gr, 0) == -1 {
+        let groups_ids = {
+            let name = CString::new(username)?;
+            let mut num_gr: c_int = 8;
+            let mut groups_ids = vec![0; num_gr as usize];
+
+            let mut res =
+                unsafe { _getgroupsbymember(name.as_ptr(), groups_ids.as_mut_ptr(), num_gr, 0) };
+            if res == -1 {
                 // Fist we tried with the pre-defined one, now we get the true max
-                num_gr = sysconf(_SC_NGROUPS_MAX) as c_int;
+                num_gr = unsafe { sysconf(_SC_NGROUPS_MAX) } as c_int;
                 groups_ids.resize(num_gr as usize, 0);
-                res = _getgroupsbymember(name, groups_ids.as_mut_ptr(), num_gr, 0);
+                res = unsafe {
+                    _getgroupsbymember(name.as_ptr(), groups_ids.as_mut_ptr(), num_gr, 0)
+                };
             }
-            groups_ids.set_len(num_gr as usize);
-        }
-
-        if res == -1 && (cfg!(target_os = "solaris") || cfg!(target_os = "illumos")) {
-            return Err(GetGroupFailed("_getgroupsbymember", res).into());
-        } else if res == -1 {
-            return Err(GetGroupFailed("getgrouplist", res).into());
-        }
 
-        groups_ids.truncate(num_gr as usize);
-
-        let groups = {
-            let mut gs = Vec::with_capacity(num_gr as usize);
-            for gid in groups_ids {
-                #[cfg(not(target_os = "macos"))]
-                let gr = Group::from_gid(gid)?;
-                #[cfg(target_os = "macos")]
-                let gr = Group::from_gid(gid as u32)?;
-
-                gs.push(gr);
+            if res == -1 {
+                return Err(GetGroupFailed("_getgroupsbymember", res).into());
             }
-            gs
+            groups_ids.truncate(res as usize);
+            groups_ids.into_iter().map(Gid::from).collect::<Vec<_>>()
         };
 
-        Ok(Groups { inner: groups })
+        let groups: io::Result<Vec<Group>> = groups_ids.into_iter().map(Group::from_gid).collect();
+
+        groups.map(|groups| Self { inner: groups })
+    }
+
+    /// Get all the groups of the system group database, in the order of the database.
+    ///
+    /// This is not thread-safe relative to other calls of the `getgrent` family of functions,
+    /// as they all share the same cursor on the database.
+    ///
+    /// # Errors
+    /// If it fails to convert a entry of the database into a [`Group`], an error variant will
+    /// be returned.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn all() -> io::Result<Self> {
+        let groups: Result<Vec<Group>, Error> = GroupIter::new().collect();
+
+        Ok(Groups { inner: groups? })
     }
 
     /// Get groups from a list of group names.
@@ -515,8 +546,274 @@ impl IntoIterator for Groups {
     }
 }
 
+/// Parses `id` as a group ID, if it's made only of digits.
+fn parse_gid(id: &BStr) -> Option<Gid> {
+    if id.is_empty() || !id.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    id.to_str().ok()?.parse::<libc::gid_t>().ok().map(Gid::from)
+}
+
+/// Whether `err` is a [`GroupNotFound`] error.
+fn is_not_found(err: &IoError) -> bool {
+    matches!(err.get_ref().and_then(|err| err.downcast_ref()), Some(GroupNotFound))
+}
+
+/// Get the IDs of the supplementary groups of the calling process, as `getgroups` gives them.
+fn caller_gids() -> io::Result<Vec<gid_t>> {
+    // First we check if we indeed have groups.
+    // "If gidsetsize is 0 (fist parameter), getgroups() returns the number of supplementary
+    // group IDs associated with the calling process without modifying the array
+    // pointed to by grouplist."
+    let num_groups = unsafe { getgroups(0, ptr::null_mut()) };
+    if num_groups == -1 {
+        return Err(IoError::last_os_error());
+    }
+
+    let mut groups_ids = Vec::with_capacity(num_groups as usize);
+    let num_groups = unsafe { getgroups(num_groups, groups_ids.as_mut_ptr()) };
+    if num_groups == -1 {
+        return Err(IoError::last_os_error());
+    } else {
+        unsafe {
+            groups_ids.set_len(num_groups as usize);
+        }
+    }
+
+    Ok(groups_ids)
+}
+
+/// Get the IDs of the groups of the calling process, as `id` shows them: the effective group
+/// first, followed by the supplementary groups in the order given by the system.
+///
+/// Unlike the list of `getgroups`, each ID is there only once, and the effective group is
+/// always there.
+///
+/// # Errors
+/// If the supplementary groups cannot be retrieved, an error variant will be returned.
+///
+/// ## Example
+/// ```rust
+/// use coreutils_core::os::group::{current_groups_ordered, Group};
+///
+/// let groups = current_groups_ordered().unwrap();
+///
+/// assert_eq!(Group::new().unwrap().id(), groups[0]);
+/// ```
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn current_groups_ordered() -> io::Result<Vec<Gid>> {
+    let egid = Gid::from(unsafe { getegid() });
+    let supplementary = caller_gids()?.into_iter().map(Gid::from);
+
+    Ok(primary_first(egid, supplementary))
+}
+
+/// Put `primary` before the `others` IDs, keeping only the first of each ID.
+fn primary_first(primary: Gid, others: impl IntoIterator<Item = Gid>) -> Vec<Gid> {
+    let mut seen = HashSet::new();
+    iter::once(primary).chain(others).filter(|&id| seen.insert(id)).collect()
+}
+
+/// Get the IDs of all the groups that the user `name` belongs, including its `primary` group.
+///
+/// # Errors
+/// If `name` contains a NUL byte, or if the user is in more than 65536 groups, an error variant
+/// will be returned.
+#[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn groups_of_user(name: &BStr, primary: Gid) -> io::Result<Vec<Gid>> {
+    let name = CString::new(name.as_bytes())?;
+    // macOS takes the group IDs as `c_int`
+    #[cfg(target_os = "macos")]
+    let primary = primary.as_raw() as c_int;
+    #[cfg(not(target_os = "macos"))]
+    let primary = primary.as_raw();
+    let mut num_groups: c_int = 16;
+
+    loop {
+        let mut groups_ids = vec![0; num_groups as usize];
+        let res = unsafe {
+            getgrouplist(name.as_ptr(), primary, groups_ids.as_mut_ptr(), &mut num_groups)
+        };
+
+        if res != -1 {
+            groups_ids.truncate(num_groups as usize);
+
+            #[cfg(target_os = "macos")]
+            let groups_ids =
+                groups_ids.into_iter().map(|gid| Gid::from(gid as libc::gid_t)).collect();
+            #[cfg(not(target_os = "macos"))]
+            let groups_ids = groups_ids.into_iter().map(Gid::from).collect();
+            break Ok(groups_ids);
+        }
+
+        num_groups = match grown_num_groups(num_groups, groups_ids.len()) {
+            Some(num_groups) => num_groups,
+            None => break Err(GetGroupFailed("getgrouplist", libc::ERANGE).into()),
+        };
+    }
+}
+
+/// Number of groups to call [`getgrouplist`] again with, after they didn't fit in `len` and it
+/// reported `num_groups`, or `None` if that is more than [`MAX_GROUPS`].
+///
+/// Most systems report the number of groups needed, the others leave the size of the buffer,
+/// so it is doubled.
+#[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+fn grown_num_groups(num_groups: c_int, len: usize) -> Option<c_int> {
+    let len = len as c_int;
+    let num_groups = if num_groups > len { num_groups } else { len.saturating_mul(2) };
+    if num_groups <= MAX_GROUPS { Some(num_groups) } else { None }
+}
+
+/// A cache of groups by ID, for utilities that look up the same groups many times, like
+/// `ls -l` does for the group of every file.
+///
+/// Both the groups found and the IDs without a group are remembered, so the system group
+/// database is looked up at most once for each ID. Nothing is shared between caches.
+///
+/// ## Example
+/// ```rust
+/// use coreutils_core::os::group::{Group, GroupCache};
+///
+/// let group = Group::new().unwrap();
+/// let mut cache = GroupCache::new();
+///
+/// assert_eq!(Some(group.name()), cache.name_for(group.id()));
+/// ```
+#[derive(Debug)]
+pub struct GroupCache<F = fn(Gid) -> io::Result<Group>> {
+    groups: HashMap<Gid, Option<Group>>,
+    lookup: F,
+}
+
+impl GroupCache {
+    /// Creates a empty [`GroupCache`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_lookup(Group::from_gid)
+    }
+}
+
+impl Default for GroupCache {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: FnMut(Gid) -> io::Result<Group>> GroupCache<F> {
+    /// Creates a empty cache of the groups given by `lookup`.
+    #[inline]
+    fn with_lookup(lookup: F) -> Self {
+        GroupCache { groups: HashMap::new(), lookup }
+    }
+
+    /// Get the group with the `id`, or `None` if there is no such group.
+    ///
+    /// Errors while looking up the group are taken as the group not existing.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get(&mut self, id: impl Into<Gid>) -> Option<&Group> {
+        let lookup = &mut self.lookup;
+        let id = id.into();
+        self.groups.entry(id).or_insert_with(|| lookup(id).ok()).as_ref()
+    }
+
+    /// Get the name of the group with the `id`, or `None` if there is no such group.
+    #[inline]
+    pub fn name_for(&mut self, id: impl Into<Gid>) -> Option<&BStr> {
+        self.get(id).map(Group::name)
+    }
+}
+
+/// Iterator over the groups of the system group database, in the order of the database.
+///
+/// The database is rewound when created, and closed when dropped, even if the iteration
+/// stopped early. libc holds a single cursor on the database, so only one [`GroupIter`]
+/// should exist at a time, and it must not be used together with other calls of the
+/// `getgrent` family of functions.
+#[derive(Debug)]
+pub struct GroupIter {
+    _private: (),
+}
+
+impl GroupIter {
+    /// Creates a [`GroupIter`] starting at the first group of the database.
+    #[inline]
+    pub fn new() -> Self {
+        unsafe { setgrent() };
+        GroupIter { _private: () }
+    }
+}
+
+impl Default for GroupIter {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for GroupIter {
+    type Item = Result<Group, Error>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let gr = unsafe { getgrent() };
+        if gr.is_null() {
+            None
+        } else {
+            Some(Group::try_from(unsafe { *gr }))
+        }
+    }
+}
+
+impl Drop for GroupIter {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { endgrent() };
+    }
+}
 
 // Extra traits
+/// [`Group`] as serialized, with the names as (lossy) UTF-8 strings.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerdeGroup {
+    name: String,
+    id: Gid,
+    passwd: String,
+    members: Vec<String>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Group {
+    #[inline]
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerdeGroup {
+            name: self.name.to_str_lossy().into_owned(),
+            id: self.id,
+            passwd: self.passwd.to_str_lossy().into_owned(),
+            members: self.mem.iter().map(|m| m.to_str_lossy().into_owned()).collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Group {
+    #[inline]
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let gr = SerdeGroup::deserialize(deserializer)?;
+
+        Ok(Group {
+            name: BString::from(gr.name),
+            id: gr.id,
+            passwd: BString::from(gr.passwd),
+            mem: gr.members.into_iter().map(BString::from).collect(),
+        })
+    }
+}
+
 impl From<Group> for group {
     #[inline]
     fn from(mut gr: Group) -> Self {
@@ -525,9 +822,329 @@ impl From<Group> for group {
 
         group {
             gr_name: gr.name.as_mut_ptr() as *mut c_char,
-            gr_gid: gr.id,
+            gr_gid: gr.id.as_raw(),
             gr_passwd: gr.passwd.as_mut_ptr() as *mut c_char,
             gr_mem: vec.as_mut_ptr(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::testutil::lock_shared_state;
+
+    #[test]
+    #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+    fn group_list_grows_up_to_max() {
+        assert_eq!(Some(40), grown_num_groups(40, 16));
+        assert_eq!(Some(32), grown_num_groups(16, 16));
+        assert_eq!(Some(32), grown_num_groups(-1, 16));
+        assert_eq!(Some(MAX_GROUPS), grown_num_groups(16, MAX_GROUPS as usize / 2));
+        assert_eq!(None, grown_num_groups(16, MAX_GROUPS as usize));
+        assert_eq!(None, grown_num_groups(MAX_GROUPS + 1, 16));
+    }
+
+    #[test]
+    fn group_is_copied_out_of_libc_buffer() {
+        // The fields are copied, not owned, so dropping a group must not free the memory of
+        // libc, and a second lookup must give the same group
+        let first = Group::new().unwrap();
+        let second = Group::new().unwrap();
+        assert_eq!(first, second);
+
+        let by_gid = Group::from_gid(first.id()).unwrap();
+        drop(first);
+        assert_eq!(second, by_gid);
+    }
+
+    #[test]
+    fn missing_group_is_not_found() {
+        let err = Group::from_gid(999_999_999).unwrap_err();
+        assert!(is_not_found(&err));
+
+        let err = Group::from_name("definitely_no_such_group").unwrap_err();
+        assert!(is_not_found(&err));
+    }
+
+    #[test]
+    fn group_has_all_members() {
+        let content = std::fs::read_to_string("/etc/group").unwrap();
+
+        // Every group of the database, members included, as listed in `/etc/group`
+        for line in content.lines().filter(|line| !line.starts_with('#')) {
+            let fields: Vec<_> = line.split(':').collect();
+            if fields.len() != 4 {
+                continue;
+            }
+            let members: Vec<_> = fields[3].split(',').filter(|m| !m.is_empty()).collect();
+
+            let group = Group::from_name(fields[0]).unwrap();
+            assert_eq!(members, group.members());
+        }
+    }
+
+    #[test]
+    fn group_from_name() {
+        let group = Group::new().unwrap();
+        let name = group.name().to_str().unwrap();
+
+        assert_eq!(group, Group::from_name(name).unwrap());
+    }
+
+    #[test]
+    fn name_with_nul_byte_is_invalid() {
+        let is_invalid_name = |err: &io::Error| {
+            matches!(
+                err.get_ref().and_then(|err| err.downcast_ref()),
+                Some(InvalidName(name)) if name == "foo\0bar"
+            )
+        };
+
+        // The name would be cut to `foo` by the C call
+        assert!(is_invalid_name(&Group::from_name_bytes(b"foo\0bar".as_bstr()).unwrap_err()));
+        assert!(is_invalid_name(&Group::from_name("foo\0bar").unwrap_err()));
+        assert!(is_invalid_name(&Group::resolve(b"foo\0bar".as_bstr()).unwrap_err()));
+        assert!(!Group::exists_name(b"root\0".as_bstr()));
+    }
+
+    #[test]
+    fn group_from_name_bytes() {
+        let group = Group::new().unwrap();
+
+        assert_eq!(group, Group::from_name_bytes(group.name()).unwrap());
+        assert!(is_not_found(&Group::from_name_bytes(b"no_such_group\xff".as_bstr()).unwrap_err()));
+    }
+
+    #[test]
+    fn resolve_name() {
+        let group = Group::new().unwrap();
+
+        assert_eq!(group, Group::resolve(group.name()).unwrap());
+        assert!(is_not_found(&Group::resolve(b"definitely_no_such_group".as_bstr()).unwrap_err()));
+    }
+
+    #[test]
+    fn resolve_numeric_id() {
+        let group = Group::new().unwrap();
+        let id = group.id().to_string();
+
+        assert_eq!(group, Group::resolve(id.as_bytes().as_bstr()).unwrap());
+        assert!(is_not_found(&Group::resolve(b"999999999".as_bstr()).unwrap_err()));
+    }
+
+    #[test]
+    fn resolve_forced_numeric_id() {
+        let group = Group::new().unwrap();
+        let id = format!("+{}", group.id());
+        let name = format!("+{}", group.name());
+
+        assert_eq!(group, Group::resolve(id.as_bytes().as_bstr()).unwrap());
+        // The name is not looked up
+        assert!(is_not_found(&Group::resolve(name.as_bytes().as_bstr()).unwrap_err()));
+        assert!(is_not_found(&Group::resolve(b"+".as_bstr()).unwrap_err()));
+    }
+
+    #[test]
+    fn resolve_numeric_looking_name() {
+        // Only specs made of digits are taken as IDs
+        for spec in &["0abc", "-0", "0x0", " 0", "1e3"] {
+            assert!(is_not_found(&Group::resolve(spec.as_bytes().as_bstr()).unwrap_err()));
+        }
+        assert_eq!(None, parse_gid(b"10000000000".as_bstr()));
+        assert_eq!(Some(Gid::from(42)), parse_gid(b"0042".as_bstr()));
+    }
+
+    #[test]
+    fn current_groups_have_egid_first() {
+        let groups = current_groups_ordered().unwrap();
+
+        assert_eq!(Gid::from(unsafe { getegid() }), groups[0]);
+        let unique: HashSet<_> = groups.iter().collect();
+        assert_eq!(groups.len(), unique.len());
+    }
+
+    #[test]
+    fn primary_first_removes_duplicates() {
+        let ids = |ids: &[gid_t]| ids.iter().copied().map(Gid::from).collect::<Vec<_>>();
+
+        assert_eq!(ids(&[10, 3, 7, 1]), primary_first(Gid::from(10), ids(&[3, 7, 3, 10, 1, 7])));
+        assert_eq!(ids(&[5]), primary_first(Gid::from(5), Vec::new()));
+        assert_eq!(ids(&[5]), primary_first(Gid::from(5), ids(&[5, 5])));
+    }
+
+    #[test]
+    fn group_cache_looks_up_once() {
+        let lookups = std::cell::Cell::new(0);
+        let mut cache = GroupCache::with_lookup(|id| {
+            lookups.set(lookups.get() + 1);
+            if id == 42 {
+                let name = BString::from("answer");
+                Ok(Group { name, id, passwd: BString::from("x"), mem: Vec::new() })
+            } else {
+                Err(GroupNotFound.into())
+            }
+        });
+
+        assert_eq!(Some(b"answer".as_bstr()), cache.name_for(42));
+        assert_eq!(Some(b"answer".as_bstr()), cache.name_for(42));
+        assert_eq!(1, lookups.get());
+
+        // Misses are remembered too
+        assert_eq!(None, cache.name_for(7));
+        assert_eq!(None, cache.get(7));
+        assert_eq!(2, lookups.get());
+    }
+
+    #[test]
+    fn group_cache_of_system_groups() {
+        let group = Group::new().unwrap();
+        let mut cache = GroupCache::new();
+
+        assert_eq!(Some(&group), cache.get(group.id()));
+        assert_eq!(None, cache.name_for(999_999_999));
+    }
+
+    #[test]
+    fn concurrent_group_lookups_agree() {
+        let gid = unsafe { getegid() };
+        let lookups: Vec<_> =
+            (0..8).map(|_| std::thread::spawn(move || Group::from_gid(gid).unwrap())).collect();
+
+        let groups: Vec<_> = lookups.into_iter().map(|lookup| lookup.join().unwrap()).collect();
+        assert!(groups.iter().all(|group| *group == groups[0]));
+        assert_eq!(groups[0].id(), gid);
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(
+            "Failed to get group with the following error code: 13. For more info search for the \
+             getgrgid_r manual",
+            GetGroupFailed("getgrgid_r", 13).to_string()
+        );
+        assert_eq!(
+            "Group name check failed, `.gr_name` field is null",
+            NameCheckFailed.to_string()
+        );
+        assert_eq!(
+            "Group passwd check failed, `.gr_passwd` is null",
+            PasswdCheckFailed.to_string()
+        );
+        assert_eq!("Group was not found in the system", GroupNotFound.to_string());
+        assert_eq!(
+            "Invalid group name \"a\\0b\": it has a NUL byte",
+            InvalidName(BString::from("a\0b")).to_string()
+        );
+
+        let err = Error::from(CString::new("a\0b").unwrap_err());
+        assert!(err.to_string().starts_with("Failed to create CString: "));
+        assert!(err.source().is_some());
+
+        // Usable as a boxed error
+        let err: Box<dyn StdError> = Box::new(GroupNotFound);
+        assert_eq!("Group was not found in the system", err.to_string());
+    }
+
+    #[test]
+    fn all_groups_has_root_group() {
+        // libc holds a single cursor on the group database, so it is read by one test at a time
+        let _grent = lock_shared_state();
+        let groups = Groups::all().unwrap();
+
+        assert!(groups.iter().any(|group| group.id() == 0));
+        assert!(groups.iter().any(|group| *group == Group::new().unwrap()));
+    }
+
+    #[test]
+    fn group_iter_stops_early() {
+        // libc holds a single cursor on the group database, so it is read by one test at a time
+        let _grent = lock_shared_state();
+        let first: Vec<_> = GroupIter::new().take(3).map(Result::unwrap).collect();
+
+        // A new iterator starts again from the first group
+        let again: Vec<_> = GroupIter::new().take(3).map(Result::unwrap).collect();
+        assert_eq!(first, again);
+        assert_eq!(first, Groups::all().unwrap().into_vec()[..first.len()]);
+    }
+
+    #[test]
+    fn groups_of_root_user() {
+        let groups_ids = groups_of_user(b"root".as_bstr(), Gid::from(0)).unwrap();
+        assert!(groups_ids.contains(&Gid::from(0)));
+
+        let groups = Groups::from_username("root").unwrap();
+        let ids: Vec<_> = groups.iter().map(Group::id).collect();
+        assert_eq!(groups_ids, ids);
+    }
+
+    #[test]
+    fn group_exists() {
+        let group = Group::new().unwrap();
+
+        assert!(Group::exists_name(group.name()));
+        assert!(Group::exists_gid(group.id()));
+        assert!(!Group::exists_name(b"definitely_no_such_group".as_bstr()));
+        assert!(!Group::exists_gid(999_999_999));
+    }
+
+    #[test]
+    fn group_members() {
+        let group = Group {
+            name: BString::from("staff"),
+            id: Gid::from(50),
+            passwd: BString::from("x"),
+            mem: vec![BString::from("alice"), BString::from("bob")],
+        };
+
+        assert!(group.is_member(b"bob".as_bstr()));
+        assert!(!group.is_member(b"carol".as_bstr()));
+        assert_eq!(2, group.member_count());
+        assert_eq!(group.members().len(), group.member_count());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn group_serde_round_trip() {
+        let group = Group {
+            name: BString::from("staff"),
+            id: Gid::from(50),
+            passwd: BString::from("x"),
+            mem: vec![BString::from("alice"), BString::from("bob")],
+        };
+
+        let json = serde_json::to_string(&group).unwrap();
+        assert_eq!(
+            r#"{"name":"staff","id":50,"passwd":"x","members":["alice","bob"]}"#,
+            json
+        );
+        assert_eq!(group, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn groups_are_identified_by_id() {
+        let staff = Group {
+            name: BString::from("staff"),
+            id: Gid::from(50),
+            passwd: BString::from("x"),
+            mem: vec![BString::from("alice")],
+        };
+        let renamed = Group { name: BString::from("crew"), mem: Members::new(), ..staff.clone() };
+
+        let set: HashSet<_> = vec![staff.clone(), renamed.clone()].into_iter().collect();
+        assert_eq!(1, set.len());
+        assert_eq!(staff, renamed);
+        assert!(staff < Group { id: Gid::from(51), ..renamed });
+    }
+
+    #[test]
+    fn lossy_name_of_invalid_utf8() {
+        let mut group = Group::new().unwrap();
+        group.name = BString::from(&b"na\xffme"[..]);
+
+        assert_eq!("na\u{fffd}me", group.name_lossy());
+        assert_eq!(b"na\xffme", group.name().as_bytes());
+    }
+}
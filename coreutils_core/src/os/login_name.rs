@@ -1,11 +1,18 @@
 //! Module to abstract libc function that get user login name.
 
-use std::ffi::CStr;
+use std::{
+    ffi::CStr,
+    os::raw::{c_char, c_int},
+};
 
 use bstr::BString;
 // libc crate doesnt have getlogin_r, cuserid on linux target
 // use libc::{getlogin, getlogin_r, cuserid};
-use libc::getlogin;
+use libc::{getlogin, size_t, ERANGE};
+
+extern "C" {
+    fn getlogin_r(buf: *mut c_char, bufsize: size_t) -> c_int;
+}
 
 /// Returns the the name of the user logged in on the controlling terminal of the process
 /// if found.
@@ -20,3 +27,40 @@ pub fn user_login_name() -> Option<BString> {
         Some(BString::from(name.to_bytes()))
     }
 }
+
+/// Returns the the name of the user logged in on the controlling terminal of the process
+/// if found.
+///
+/// Unlike [`user_login_name`], it is thread-safe. The name can differ from the user running
+/// the process, like when running under `sudo`.
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn login_name() -> Option<BString> {
+    // Usual value of `LOGIN_NAME_MAX`, grown if it is not enough
+    let mut buff: Vec<c_char> = vec![0; 256];
+
+    loop {
+        let res = unsafe { getlogin_r(buff.as_mut_ptr(), buff.len()) };
+
+        if res == 0 {
+            let name = unsafe { CStr::from_ptr(buff.as_ptr()) }.to_bytes();
+            break if name.is_empty() { None } else { Some(BString::from(name)) };
+        } else if res == ERANGE {
+            buff.resize(buff.len() * 2, 0);
+        } else {
+            break None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn login_name_is_not_empty() {
+        // There may be no controlling terminal, like in CI
+        if let Some(name) = login_name() {
+            assert!(!name.is_empty());
+        }
+    }
+}
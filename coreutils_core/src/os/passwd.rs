@@ -1,6 +1,7 @@
 //! Module to deal more easily with UNIX passwd.
 
 use std::{
+    borrow::Cow,
     convert::TryFrom,
     error::Error as StdError,
     ffi::{CStr, CString, NulError},
@@ -13,7 +14,7 @@ use std::{
 };
 
 use bstr::{BStr, BString, ByteSlice};
-use libc::{geteuid, getpwnam_r, getpwuid_r, getuid, passwd};
+use libc::{endpwent, geteuid, getpwent, getpwnam_r, getpwuid_r, getuid, passwd, setpwent};
 
 use self::Error::*;
 #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
@@ -28,9 +29,12 @@ use super::Fields;
 use super::Time;
 use super::{
     group::{Error as GrError, Groups},
-    Gid, Uid,
+    retry_on_erange, Gid, Uid, MAX_BUFF_SIZE,
 };
 
+/// Size of the buffer first given to [`getpwuid_r`] and [`getpwnam_r`].
+const BUFF_SIZE: usize = 16384; // Got this size from manual page about `getpwuid_r`.
+
 /// This struct holds information about a passwd of UNIX/UNIX-like systems.
 ///
 /// Contains `sys/types.h` [`passwd`] struct attributes as Rust more powefull types.
@@ -185,39 +189,7 @@ impl Passwd {
     /// into [`Passwd`], an error variant is returned.
     #[cfg_attr(feature = "inline-more", inline)]
     pub fn effective() -> io::Result<Self> {
-        let mut pw = MaybeUninit::uninit();
-        let mut result = ptr::null_mut();
-        let buff_size = 16384; // Got this size from manual page about getpwuid_r
-        let mut buff = Vec::with_capacity(buff_size);
-
-        loop {
-            let error_flag = unsafe {
-                getpwuid_r(
-                    geteuid(),
-                    pw.as_mut_ptr(),
-                    buff.as_mut_ptr(),
-                    buff.capacity(),
-                    &mut result,
-                )
-            };
-
-            if error_flag == 0 {
-                if result.is_null() {
-                    break Err(PasswdNotFound.into());
-                } else {
-                    // Now that pw is initialized we get it
-                    let pw = unsafe { pw.assume_init() };
-
-                    break Ok(Passwd::try_from(pw)?);
-                }
-            } else if let Some(libc::ERANGE) = IoError::last_os_error().raw_os_error() {
-                // If there was a ERANGE error, that means the buffer was too small, so we add more
-                // `buff_size` each time we get that error
-                buff.reserve(buff_size);
-            } else {
-                break Err(IoError::last_os_error());
-            }
-        }
+        Self::from_uid(unsafe { geteuid() })
     }
 
     /// Creates a new [`Passwd`] getting the current process user passwd as default using
@@ -228,39 +200,7 @@ impl Passwd {
     /// into [`Passwd`], an error variant is returned.
     #[cfg_attr(feature = "inline-more", inline)]
     pub fn real() -> io::Result<Self> {
-        let mut pw = MaybeUninit::uninit();
-        let mut result = ptr::null_mut();
-        let buff_size = 16384; // Got this size from manual page about getpwuid_r
-        let mut buff = Vec::with_capacity(buff_size);
-
-        loop {
-            let error_flag = unsafe {
-                getpwuid_r(
-                    getuid(),
-                    pw.as_mut_ptr(),
-                    buff.as_mut_ptr(),
-                    buff.capacity(),
-                    &mut result,
-                )
-            };
-
-            if error_flag == 0 {
-                if result.is_null() {
-                    break Err(PasswdNotFound.into());
-                } else {
-                    // Now that pw is initialized we get it
-                    let pw = unsafe { pw.assume_init() };
-
-                    break Ok(Passwd::try_from(pw)?);
-                }
-            } else if let Some(libc::ERANGE) = IoError::last_os_error().raw_os_error() {
-                // If there was a ERANGE error, that means the buffer was too small, so we add more
-                // `buff_size` each time we get that error
-                buff.reserve(buff_size);
-            } else {
-                break Err(IoError::last_os_error());
-            }
-        }
+        Self::from_uid(unsafe { getuid() })
     }
 
     /// Creates a new [`Passwd`] using a `id` to get all attributes.
@@ -269,33 +209,31 @@ impl Passwd {
     /// If there is a error ocurrence when getting [`passwd`] (C struct) or converting it
     /// into [`Passwd`], an error variant is returned.
     #[cfg_attr(feature = "inline-more", inline)]
-    pub fn from_uid(id: Uid) -> io::Result<Self> {
+    pub fn from_uid(id: impl Into<Uid>) -> io::Result<Self> {
+        let id = id.into();
         let mut pw = MaybeUninit::uninit();
         let mut result = ptr::null_mut();
-        let buff_size = 16384; // Got this size from manual page about getpwuid_r
-        let mut buff = Vec::with_capacity(buff_size);
-
-        loop {
-            let error_flag = unsafe {
-                getpwuid_r(id, pw.as_mut_ptr(), buff.as_mut_ptr(), buff.capacity(), &mut result)
-            };
-
-            if error_flag == 0 {
-                if result.is_null() {
-                    break Err(PasswdNotFound.into());
-                } else {
-                    // Now that pw is initialized we get it
-                    let pw = unsafe { pw.assume_init() };
-
-                    break Ok(Passwd::try_from(pw)?);
-                }
-            } else if let Some(libc::ERANGE) = IoError::last_os_error().raw_os_error() {
-                // If there was a ERANGE error, that means the buffer was too small, so we add more
-                // `buff_size` each time we get that error
-                buff.reserve(buff_size);
-            } else {
-                break Err(IoError::last_os_error());
-            }
+        let mut buff = Vec::with_capacity(BUFF_SIZE);
+
+        let error_flag = retry_on_erange(&mut buff, MAX_BUFF_SIZE, |buff| unsafe {
+            getpwuid_r(
+                id.as_raw(),
+                pw.as_mut_ptr(),
+                buff.as_mut_ptr(),
+                buff.capacity(),
+                &mut result,
+            )
+        });
+
+        match error_flag {
+            0 if result.is_null() => Err(PasswdNotFound.into()),
+            0 => {
+                // Now that pw is initialized we get it
+                let pw = unsafe { pw.assume_init() };
+
+                Ok(Passwd::try_from(pw)?)
+            },
+            _ => Err(GetPasswdFailed("getpwuid_r", error_flag).into()),
         }
     }
 
@@ -308,38 +246,29 @@ impl Passwd {
     pub fn from_name(name: &str) -> io::Result<Self> {
         let mut pw = MaybeUninit::uninit();
         let mut result = ptr::null_mut();
-        let buff_size = 16384; // Got this size from manual page about getpwuid_r
-        let mut buff = Vec::with_capacity(buff_size);
+        let mut buff = Vec::with_capacity(BUFF_SIZE);
 
         let name = CString::new(name)?;
 
-        loop {
-            let error_flag = unsafe {
-                getpwnam_r(
-                    name.as_ptr() as *const c_char,
-                    pw.as_mut_ptr(),
-                    buff.as_mut_ptr(),
-                    buff.capacity(),
-                    &mut result,
-                )
-            };
-
-            if error_flag == 0 {
-                if result.is_null() {
-                    break Err(PasswdNotFound.into());
-                } else {
-                    // Now that pw is initialized we get it
-                    let pw = unsafe { pw.assume_init() };
-
-                    break Ok(Passwd::try_from(pw)?);
-                }
-            } else if let Some(libc::ERANGE) = IoError::last_os_error().raw_os_error() {
-                // If there was a ERANGE error, that means the buffer was too small, so we add more
-                // `buff_size` each time we get that error
-                buff.reserve(buff_size);
-            } else {
-                break Err(IoError::last_os_error());
-            }
+        let error_flag = retry_on_erange(&mut buff, MAX_BUFF_SIZE, |buff| unsafe {
+            getpwnam_r(
+                name.as_ptr() as *const c_char,
+                pw.as_mut_ptr(),
+                buff.as_mut_ptr(),
+                buff.capacity(),
+                &mut result,
+            )
+        });
+
+        match error_flag {
+            0 if result.is_null() => Err(PasswdNotFound.into()),
+            0 => {
+                // Now that pw is initialized we get it
+                let pw = unsafe { pw.assume_init() };
+
+                Ok(Passwd::try_from(pw)?)
+            },
+            _ => Err(GetPasswdFailed("getpwnam_r", error_flag).into()),
         }
     }
 
@@ -349,6 +278,15 @@ impl Passwd {
         self.name.as_bstr()
     }
 
+    /// Returns the [`Passwd`] (user) login name as UTF-8, with the invalid sequences replaced
+    /// by the replacement character `U+FFFD`.
+    ///
+    /// Use [`name`](Passwd::name) to get the exact bytes of the name.
+    #[inline]
+    pub fn name_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.name)
+    }
+
     /// Returns the [`Passwd`] (user) encrypted password.
     #[inline]
     pub fn passwd(&self) -> &BStr {
@@ -438,16 +376,84 @@ impl Passwd {
     /// variant is returned.
     #[inline]
     pub fn belongs_to(&self) -> io::Result<Groups> {
-        let name = {
-            let mut n = self.name.to_string();
-            n.push('\0');
-            n
-        };
-        let gr = Groups::from_username(&name)?;
+        let gr = Groups::from_username(&self.name.to_str_lossy())?;
         Ok(gr)
     }
 }
 
+/// Returns the name of the user running the process, using the effective user id.
+///
+/// # Errors
+/// If it fails to get the [`Passwd`] of the user, an error variant is returned.
+#[inline]
+pub fn current_username() -> io::Result<BString> {
+    Passwd::effective().map(|pw| pw.name)
+}
+
+/// Get all the users of the system password database, in the order of the database.
+///
+/// This is not thread-safe relative to other calls of the `getpwent` family of functions, as
+/// they all share the same cursor on the database.
+///
+/// # Errors
+/// If it fails to convert a entry of the database into a [`Passwd`], an error variant will be
+/// returned.
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn all_users() -> io::Result<Vec<Passwd>> {
+    let users: StdResult<Vec<Passwd>, Error> = PasswdIter::new().collect();
+
+    Ok(users?)
+}
+
+/// Iterator over the users of the system password database, in the order of the database.
+///
+/// The database is rewound when created, and closed when dropped, even if the iteration
+/// stopped early. libc holds a single cursor on the database, so only one [`PasswdIter`]
+/// should exist at a time, and it must not be used together with other calls of the
+/// `getpwent` family of functions.
+#[derive(Debug)]
+pub struct PasswdIter {
+    _private: (),
+}
+
+impl PasswdIter {
+    /// Creates a [`PasswdIter`] starting at the first user of the database.
+    #[inline]
+    pub fn new() -> Self {
+        unsafe { setpwent() };
+        PasswdIter { _private: () }
+    }
+}
+
+impl Default for PasswdIter {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for PasswdIter {
+    type Item = StdResult<Passwd, Error>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let pw = unsafe { getpwent() };
+        if pw.is_null() {
+            None
+        } else {
+            // The fields are copied out of the buffer of libc, that the next call overwrites
+            Some(Passwd::try_from(unsafe { *pw }))
+        }
+    }
+}
+
+impl Drop for PasswdIter {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { endpwent() };
+    }
+}
+
 impl TryFrom<passwd> for Passwd {
     type Error = Error;
 
@@ -467,9 +473,9 @@ impl TryFrom<passwd> for Passwd {
             BString::from(passwd_cstr.to_bytes())
         };
 
-        let user_id = pw.pw_uid;
+        let user_id = Uid::from(pw.pw_uid);
 
-        let group_id = pw.pw_gid;
+        let group_id = Gid::from(pw.pw_gid);
 
         let gecos = if pw.pw_gecos.is_null() {
             return Err(GecosCheckFailed);
@@ -635,8 +641,8 @@ impl From<Passwd> for passwd {
         passwd {
             pw_name: pw.name.as_mut_ptr() as *mut c_char,
             pw_passwd: pw.passwd.as_mut_ptr() as *mut c_char,
-            pw_uid: pw.user_id,
-            pw_gid: pw.group_id,
+            pw_uid: pw.user_id.as_raw(),
+            pw_gid: pw.group_id.as_raw(),
             pw_gecos: pw.gecos.as_mut_ptr() as *mut c_char,
             pw_dir: pw.dir.as_mut_ptr() as *mut c_char,
             pw_shell: pw.shell.as_mut_ptr() as *mut c_char,
@@ -673,3 +679,73 @@ impl From<Passwd> for passwd {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::lock_shared_state;
+
+    fn is_passwd_not_found(err: &io::Error) -> bool {
+        matches!(err.get_ref().and_then(|err| err.downcast_ref()), Some(PasswdNotFound))
+    }
+
+    #[test]
+    fn passwd_of_current_user() {
+        let passwd = Passwd::effective().unwrap();
+        assert_eq!(passwd.uid(), unsafe { geteuid() });
+        assert_eq!(passwd, Passwd::from_uid(passwd.uid()).unwrap());
+        assert_eq!(passwd, Passwd::from_name(passwd.name().to_str().unwrap()).unwrap());
+
+        assert_eq!(Passwd::real().unwrap().uid(), unsafe { getuid() });
+    }
+
+    #[test]
+    fn current_username_is_not_empty() {
+        let name = current_username().unwrap();
+
+        assert!(!name.is_empty());
+        assert_eq!(Passwd::effective().unwrap().name(), name);
+    }
+
+    #[test]
+    fn missing_passwd_is_not_found() {
+        let err = Passwd::from_uid(999_999_999).unwrap_err();
+        assert!(is_passwd_not_found(&err));
+
+        let err = Passwd::from_name("definitely_no_such_user").unwrap_err();
+        assert!(is_passwd_not_found(&err));
+    }
+
+    #[test]
+    fn all_users_has_root_user() {
+        // libc holds a single cursor on the password database, so it is read by one test at a
+        // time
+        let _pwent = lock_shared_state();
+        let users = all_users().unwrap();
+
+        assert!(users.iter().any(|user| user.uid() == 0 && user.name() == "root"));
+        assert!(users.iter().any(|user| *user == Passwd::effective().unwrap()));
+    }
+
+    #[test]
+    fn passwd_iter_stops_early() {
+        // libc holds a single cursor on the password database, so it is read by one test at a
+        // time
+        let _pwent = lock_shared_state();
+        let first: Vec<_> = PasswdIter::new().take(3).map(Result::unwrap).collect();
+
+        // A new iterator starts again from the first user
+        let again: Vec<_> = PasswdIter::new().take(3).map(Result::unwrap).collect();
+        assert_eq!(first, again);
+        assert_eq!(first, all_users().unwrap()[..first.len()]);
+    }
+
+    #[test]
+    fn lossy_name_of_invalid_utf8() {
+        let mut passwd = Passwd::effective().unwrap();
+        passwd.name = BString::from(&b"na\xffme"[..]);
+
+        assert_eq!("na\u{fffd}me", passwd.name_lossy());
+        assert_eq!(b"na\xffme", passwd.name().as_bytes());
+    }
+}
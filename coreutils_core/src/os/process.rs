@@ -4,11 +4,10 @@ use std::ffi::CString;
 use std::{convert::TryInto, io};
 
 use crate::{
-    libc,
+    libc::{self, gid_t},
     os::{
         group::{Group, Groups},
         passwd::Passwd,
-        Gid,
     },
 };
 
@@ -45,7 +44,7 @@ pub fn change_root(newroot: &str) -> io::Result<()> {
 pub fn set_user(user: &str) -> io::Result<()> {
     let user = Passwd::from_name(user)?;
 
-    match unsafe { libc::setuid(user.uid()) } {
+    match unsafe { libc::setuid(user.uid().as_raw()) } {
         0 => Ok(()),
         _ => Err(io::Error::last_os_error()),
     }
@@ -59,7 +58,7 @@ pub fn set_user(user: &str) -> io::Result<()> {
 #[inline]
 pub fn set_groups(groups: &[&str]) -> io::Result<()> {
     let groups = Groups::from_group_list(&groups)?;
-    let groups: Vec<Gid> = groups.iter().map(|g| g.id()).collect();
+    let groups: Vec<gid_t> = groups.iter().map(|g| g.id().as_raw()).collect();
 
     #[allow(clippy::useless_conversion)]
     let size = groups.len().try_into().unwrap_or_default();
@@ -79,7 +78,7 @@ pub fn set_groups(groups: &[&str]) -> io::Result<()> {
 pub fn set_group(group: &str) -> io::Result<()> {
     let group = Group::from_name(group)?;
 
-    match unsafe { libc::setgid(group.id()) } {
+    match unsafe { libc::setgid(group.id().as_raw()) } {
         0 => Ok(()),
         _ => Err(io::Error::last_os_error()),
     }
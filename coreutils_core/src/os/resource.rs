@@ -2,9 +2,14 @@
 //!
 //! Also holds utility functions for summarizing the data returned by getrusage(2)
 #[cfg(not(target_os = "fuchsia"))]
-use libc::getrusage;
+use std::io;
+
+#[cfg(not(target_os = "fuchsia"))]
+use libc::{getrusage, wait4};
 use libc::{c_int, rusage, RUSAGE_CHILDREN, RUSAGE_SELF};
 
+#[cfg(not(target_os = "fuchsia"))]
+use super::Pid;
 use super::TimeVal;
 
 /// Interface for `RUSAGE_*` constants from libc.
@@ -108,3 +113,32 @@ pub fn get_rusage(target: ResourceConsumer) -> RUsage {
 
     RUsage::from(usage)
 }
+
+/// Wait for the child process `pid` to terminate and get the resources used by it.
+///
+/// Unlike [`get_rusage`] with [`ResourceConsumer::Children`], the resources returned are only
+/// the ones used by `pid` (and its waited descendants), not by every child of the caller.
+///
+/// Returns the raw wait status, as set by `wait4(2)`, and the resources used.
+///
+/// # Errors
+/// If a internal call set a errno (I/O OS error), an error variant will be returned.
+#[cfg(not(target_os = "fuchsia"))]
+pub fn wait_rusage(pid: Pid) -> io::Result<(c_int, RUsage)> {
+    let mut status = 0;
+    let mut usage: rusage = unsafe { std::mem::zeroed() };
+
+    loop {
+        let res = unsafe { wait4(pid, &mut status, 0, &mut usage) };
+
+        if res != -1 {
+            break Ok((status, RUsage::from(usage)));
+        }
+
+        let err = io::Error::last_os_error();
+        // Interrupted by a signal before the child terminated, so we wait again
+        if err.kind() != io::ErrorKind::Interrupted {
+            break Err(err);
+        }
+    }
+}
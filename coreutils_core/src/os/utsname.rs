@@ -7,7 +7,7 @@ use std::{
 };
 
 use bstr::{BStr, BString, ByteSlice};
-use libc::{c_char, uname, utsname};
+use libc::{c_char, gethostname, uname, utsname};
 
 /// A struct that holds several system informations, like the system name, host name, etc.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -85,47 +85,41 @@ impl UtsName {
     }
 }
 
+/// Copy a NUL terminated `field` of [`utsname`] into a [`BString`], up to the terminator.
+#[inline]
+fn field_to_bstring(field: &[c_char]) -> BString {
+    field.iter().take_while(|&&cc| cc != 0).map(|&cc| cc as u8).collect::<Vec<_>>().into()
+}
+
+/// Get the host name of the machine.
+///
+/// # Errors
+/// If a internal call set a errno (I/O OS error), an error variant will be returned.
+#[inline]
+pub fn hostname() -> io::Result<BString> {
+    // The maximum size of a host name is 255 bytes, plus the NUL terminator
+    let mut buff: Vec<c_char> = vec![0; 256];
+
+    let res = unsafe { gethostname(buff.as_mut_ptr(), buff.len()) };
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(field_to_bstring(&buff))
+}
+
 impl TryFrom<utsname> for UtsName {
     type Error = io::Error;
 
     #[inline]
     fn try_from(uts_name: utsname) -> Result<Self, Self::Error> {
-        let sysname = {
-            let name: Vec<_> =
-                uts_name.sysname.iter().map(|&cc| cc as u8).filter(|&cc| cc != b'\0').collect();
-            BString::from(name)
-        };
-
-        let nodename = {
-            let name: Vec<_> =
-                uts_name.nodename.iter().map(|&cc| cc as u8).filter(|&cc| cc != b'\0').collect();
-            BString::from(name)
-        };
-
-        let release = {
-            let name: Vec<_> =
-                uts_name.release.iter().map(|&cc| cc as u8).filter(|&cc| cc != b'\0').collect();
-            BString::from(name)
-        };
-
-        let version = {
-            let name: Vec<_> =
-                uts_name.version.iter().map(|&cc| cc as u8).filter(|&cc| cc != b'\0').collect();
-            BString::from(name)
-        };
-
-        let machine = {
-            let name: Vec<_> =
-                uts_name.machine.iter().map(|&cc| cc as u8).filter(|&cc| cc != b'\0').collect();
-            BString::from(name)
-        };
-
+        let sysname = field_to_bstring(&uts_name.sysname);
+        let nodename = field_to_bstring(&uts_name.nodename);
+        let release = field_to_bstring(&uts_name.release);
+        let version = field_to_bstring(&uts_name.version);
+        let machine = field_to_bstring(&uts_name.machine);
         #[cfg(any(target_os = "linux", target_os = "fuchsia"))]
-        let domainname = {
-            let name: Vec<_> =
-                uts_name.domainname.iter().map(|&cc| cc as u8).filter(|&cc| cc != b'\0').collect();
-            BString::from(name)
-        };
+        let domainname = field_to_bstring(&uts_name.domainname);
 
         Ok(UtsName {
             sysname,
@@ -196,3 +190,30 @@ impl From<UtsName> for utsname {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_name_of_the_os() {
+        let uts_name = UtsName::new().unwrap();
+        assert!(!uts_name.system_name().is_empty());
+
+        #[cfg(target_os = "linux")]
+        assert_eq!("Linux", uts_name.system_name());
+        #[cfg(target_os = "macos")]
+        assert_eq!("Darwin", uts_name.system_name());
+    }
+
+    #[test]
+    fn hostname_is_node_name() {
+        assert_eq!(UtsName::new().unwrap().node_name(), hostname().unwrap());
+    }
+
+    #[test]
+    fn field_stops_at_nul() {
+        let field = [b'a' as c_char, b'b' as c_char, 0, b'c' as c_char];
+        assert_eq!("ab", field_to_bstring(&field));
+    }
+}
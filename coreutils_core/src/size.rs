@@ -0,0 +1,137 @@
+//! Helper functions to display sizes in a human readable way.
+
+/// Base of the units of a human readable size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Base {
+    /// Powers of 1000, with the SI units: `kB`, `MB`, `GB`, ...
+    Si,
+    /// Powers of 1024, with the binary units: `KiB`, `MiB`, `GiB`, ...
+    Binary,
+}
+
+impl Base {
+    /// Returns the size of one unit in the [`Base`].
+    #[inline]
+    pub const fn value(self) -> u64 {
+        match self {
+            Base::Si => 1000,
+            Base::Binary => 1024,
+        }
+    }
+
+    /// Returns the units of the [`Base`], from the smallest one.
+    #[inline]
+    const fn units(self) -> [&'static str; 6] {
+        match self {
+            Base::Si => ["kB", "MB", "GB", "TB", "PB", "EB"],
+            Base::Binary => ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+        }
+    }
+}
+
+/// Formats a number of `bytes` in a human readable way, like `1.5KiB` or `23MB`, using the units
+/// of `base`.
+///
+/// Sizes are rounded half up, with one decimal if it's lower than 10, and none otherwise. Sizes
+/// lower than one unit are shown as is.
+///
+/// ## Example
+/// ```rust
+/// use coreutils_core::size::{human_readable, Base};
+///
+/// assert_eq!("1.5KiB", human_readable(1536, Base::Binary));
+/// assert_eq!("23MB", human_readable(23_000_000, Base::Si));
+/// ```
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn human_readable(bytes: u64, base: Base) -> String {
+    let units = base.units();
+    let unit_size = u128::from(base.value());
+    let bytes = u128::from(bytes);
+
+    if bytes < unit_size {
+        return bytes.to_string();
+    }
+
+    let mut unit = 0;
+    let mut divisor = unit_size;
+    while bytes >= divisor * unit_size && unit < units.len() - 1 {
+        divisor *= unit_size;
+        unit += 1;
+    }
+
+    loop {
+        let tenths = (bytes * 10 + divisor / 2) / divisor;
+        if tenths < 100 {
+            break format!("{}.{}{}", tenths / 10, tenths % 10, units[unit]);
+        }
+
+        // If the size rounds up to a whole unit, it's shown in the next unit, like `1.0MiB`
+        // instead of `1024KiB`
+        let whole = (bytes + divisor / 2) / divisor;
+        if whole >= unit_size && unit < units.len() - 1 {
+            divisor *= unit_size;
+            unit += 1;
+            continue;
+        }
+
+        break format!("{}{}", whole, units[unit]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_bytes() {
+        assert_eq!("0", human_readable(0, Base::Si));
+        assert_eq!("0", human_readable(0, Base::Binary));
+    }
+
+    #[test]
+    fn below_one_unit() {
+        assert_eq!("999", human_readable(999, Base::Si));
+        assert_eq!("1023", human_readable(1023, Base::Binary));
+    }
+
+    #[test]
+    fn binary_sizes() {
+        assert_eq!("1.0KiB", human_readable(1024, Base::Binary));
+        assert_eq!("1.5KiB", human_readable(1536, Base::Binary));
+        assert_eq!("10KiB", human_readable(10 * 1024, Base::Binary));
+        assert_eq!("2.3MiB", human_readable(2_411_725, Base::Binary));
+        assert_eq!("4.0GiB", human_readable(4 << 30, Base::Binary));
+    }
+
+    #[test]
+    fn si_sizes() {
+        assert_eq!("1.0kB", human_readable(1000, Base::Si));
+        assert_eq!("1.0kB", human_readable(1024, Base::Si));
+        assert_eq!("1.5kB", human_readable(1500, Base::Si));
+        assert_eq!("999kB", human_readable(999_499, Base::Si));
+        assert_eq!("2.3MB", human_readable(2_300_000, Base::Si));
+    }
+
+    #[test]
+    fn rounds_half_up() {
+        assert_eq!("1.1kB", human_readable(1050, Base::Si));
+        assert_eq!("1.0kB", human_readable(1049, Base::Si));
+        assert_eq!("10kB", human_readable(9950, Base::Si));
+        assert_eq!("11kB", human_readable(10_500, Base::Si));
+    }
+
+    #[test]
+    fn rounds_up_to_next_unit() {
+        assert_eq!("1.0MB", human_readable(999_500, Base::Si));
+        assert_eq!("1.0MiB", human_readable(1024 * 1024 - 1, Base::Binary));
+    }
+
+    #[test]
+    fn petabyte_range() {
+        assert_eq!("1.0PiB", human_readable(1 << 50, Base::Binary));
+        assert_eq!("1.0PiB", human_readable((1 << 50) - 1, Base::Binary));
+        assert_eq!("1023TiB", human_readable(1023 << 40, Base::Binary));
+        assert_eq!("1.0PB", human_readable(1_000_000_000_000_000, Base::Si));
+        assert_eq!("16EiB", human_readable(u64::MAX, Base::Binary));
+    }
+}
@@ -0,0 +1,192 @@
+//! Helpers for the tests of the utilities, to work on files in a throwaway directory, and to
+//! take turns on the state of the process that the tests share.
+//!
+//! This module is only available with the `testutil` feature, that is meant to be enabled in
+//! the `dev-dependencies` of a utility.
+
+use std::{
+    env,
+    ffi::CString,
+    fs, io,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    ptr,
+    sync::{Mutex, MutexGuard, Once},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use libc::{timespec, utimensat, AT_FDCWD};
+
+use crate::mktemp::mkdtemp;
+
+/// A directory with a unique name under the temporary directory of the system, that is removed
+/// with all its content when dropped.
+///
+/// ## Example
+/// ```rust
+/// use coreutils_core::testutil::TempDir;
+///
+/// let dir = TempDir::new("example").unwrap();
+/// let file = dir.create_file("file", b"content").unwrap();
+/// assert!(file.starts_with(dir.path()));
+/// ```
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Creates a new directory, with a name starting with `name` to tell the tests apart.
+    ///
+    /// # Errors
+    /// If the directory cannot be created, an error is returned.
+    #[inline]
+    pub fn new(name: &str) -> io::Result<Self> {
+        let template = env::temp_dir().join(format!("coreutils-{}-XXXXXX", name));
+        let path = mkdtemp(&template.to_string_lossy())?;
+
+        Ok(TempDir { path: PathBuf::from(path) })
+    }
+
+    /// Returns the path of the directory.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the path of `name` inside the directory.
+    #[inline]
+    pub fn join(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.path.join(name)
+    }
+
+    /// Creates the file `name` inside the directory with `contents`, and returns its path.
+    ///
+    /// # Errors
+    /// If the file cannot be written, an error is returned.
+    #[inline]
+    pub fn create_file(&self, name: impl AsRef<Path>, contents: &[u8]) -> io::Result<PathBuf> {
+        let path = self.join(name);
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    /// Creates the empty file `name` inside the directory with the access time `atime` and the
+    /// modification time `mtime`, and returns its path.
+    ///
+    /// # Errors
+    /// If the file cannot be written or its times cannot be set, an error is returned.
+    #[inline]
+    pub fn create_file_with_times(
+        &self, name: impl AsRef<Path>, atime: SystemTime, mtime: SystemTime,
+    ) -> io::Result<PathBuf> {
+        let path = self.create_file(name, b"")?;
+        set_times(&path, atime, mtime)?;
+        Ok(path)
+    }
+}
+
+impl Drop for TempDir {
+    #[inline]
+    fn drop(&mut self) {
+        // Nothing can be done about a error here, and it must not hide the failure of a test
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Sets the access time `atime` and the modification time `mtime` of the file at `path`,
+/// following symbolic links.
+///
+/// # Errors
+/// If the times cannot be set, or if they are before the UNIX epoch, an error is returned.
+pub fn set_times(path: &Path, atime: SystemTime, mtime: SystemTime) -> io::Result<()> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let times = [to_timespec(atime)?, to_timespec(mtime)?];
+
+    if unsafe { utimensat(AT_FDCWD, path.as_ptr(), times.as_ptr(), 0) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Takes the lock of the state that the tests of a process share, like the environment
+/// variables, the signal handlers or the cursors of libc on the user databases, for a test to
+/// use it without the others running in parallel getting in the way.
+///
+/// The lock is held until the returned guard is dropped. A test that panicked while holding it
+/// doesn't keep it from the others.
+pub fn lock_shared_state() -> MutexGuard<'static, ()> {
+    static INIT: Once = Once::new();
+    // `Mutex::new` is not a `const fn` in the minimum supported version of Rust
+    static mut LOCK: *const Mutex<()> = ptr::null();
+
+    let lock = unsafe {
+        INIT.call_once(|| LOCK = Box::into_raw(Box::new(Mutex::new(()))));
+        &*LOCK
+    };
+    lock.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Converts `time` into a [`timespec`].
+fn to_timespec(time: SystemTime) -> io::Result<timespec> {
+    let since_epoch = time
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    Ok(timespec {
+        tv_sec: since_epoch.as_secs() as _,
+        tv_nsec: since_epoch.subsec_nanos() as _,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn removes_its_directory() {
+        let dir = TempDir::new("removed").unwrap();
+        let path = dir.path().to_path_buf();
+        dir.create_file("file", b"content").unwrap();
+        fs::create_dir(dir.join("sub")).unwrap();
+        assert!(path.is_dir());
+
+        drop(dir);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn directories_are_unique() {
+        let first = TempDir::new("unique").unwrap();
+        let second = TempDir::new("unique").unwrap();
+
+        assert_ne!(first.path(), second.path());
+    }
+
+    #[test]
+    fn file_with_known_times() {
+        let dir = TempDir::new("times").unwrap();
+        let atime = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let mtime = UNIX_EPOCH + Duration::new(1_100_000_000, 500_000_000);
+
+        let path = dir.create_file_with_times("file", atime, mtime).unwrap();
+
+        let meta = fs::metadata(&path).unwrap();
+        assert_eq!(atime, meta.accessed().unwrap());
+        assert_eq!(mtime, meta.modified().unwrap());
+    }
+
+    #[test]
+    fn shared_state_lock_outlives_a_panic() {
+        let panicked = thread::spawn(|| {
+            let _lock = lock_shared_state();
+            panic!("while holding the lock");
+        });
+        assert!(panicked.join().is_err());
+
+        drop(lock_shared_state());
+    }
+}
@@ -0,0 +1,238 @@
+//! Module to read the extended attributes of files, like `ls -l` needs to tell which files have
+//! some.
+//!
+//! The attributes of symbolic links themselves are read, and not the ones of the files they
+//! point to. Only Linux and macOS are supported, on other systems every function fails with a
+//! `ENOTSUP` error.
+
+use std::{ffi::CString, io, os::unix::ffi::OsStrExt, path::Path, ptr};
+
+use bstr::BString;
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+use libc::ssize_t;
+use libc::ERANGE;
+
+/// Returns the names of the extended attributes of the file at `path`, in the order given by
+/// the system.
+///
+/// A file without extended attributes has an empty list.
+///
+/// # Errors
+/// If the attributes cannot be listed, like when the file doesn't exist or the file system
+/// doesn't support extended attributes, an error is returned.
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn list(path: &Path) -> io::Result<Vec<BString>> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let names = negotiate(|buff, size| unsafe { sys::list(&path, buff.cast(), size) })?;
+
+    Ok(split_names(&names))
+}
+
+/// Returns the value of the extended attribute `name` of the file at `path`.
+///
+/// # Errors
+/// If the attribute cannot be read, like when the file doesn't have it or the file system
+/// doesn't support extended attributes, an error is returned.
+#[cfg_attr(feature = "inline-more", inline)]
+pub fn get(path: &Path, name: impl AsRef<[u8]>) -> io::Result<BString> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let name = CString::new(name.as_ref())?;
+    let value = negotiate(|buff, size| unsafe { sys::get(&path, &name, buff.cast(), size) })?;
+
+    Ok(BString::from(value))
+}
+
+/// Calls `call` a first time without a buffer to know the size of the data, then a second time
+/// with a buffer of that size to get it.
+///
+/// It starts again if the data grew between the two calls.
+fn negotiate(mut call: impl FnMut(*mut u8, usize) -> io::Result<usize>) -> io::Result<Vec<u8>> {
+    loop {
+        let size = call(ptr::null_mut(), 0)?;
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut buff = vec![0; size];
+        match call(buff.as_mut_ptr(), buff.len()) {
+            Ok(len) => {
+                buff.truncate(len);
+                return Ok(buff);
+            },
+            Err(err) if err.raw_os_error() == Some(ERANGE) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Converts the result `res` of a system call into the size it returned, or the error it set.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+fn check(res: ssize_t) -> io::Result<usize> {
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res as usize)
+    }
+}
+
+/// Splits the list of attribute `names` given by the system, where each one ends with a nul
+/// byte.
+fn split_names(names: &[u8]) -> Vec<BString> {
+    names
+        .split(|&b| b == b'\0')
+        .filter(|name| !name.is_empty())
+        .map(BString::from)
+        .collect()
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use std::{ffi::CStr, io};
+
+    use libc::{c_char, c_void, lgetxattr, llistxattr};
+
+    use super::check;
+
+    pub unsafe fn list(path: &CStr, buff: *mut c_char, size: usize) -> io::Result<usize> {
+        check(llistxattr(path.as_ptr(), buff, size))
+    }
+
+    pub unsafe fn get(
+        path: &CStr, name: &CStr, buff: *mut c_void, size: usize,
+    ) -> io::Result<usize> {
+        check(lgetxattr(path.as_ptr(), name.as_ptr(), buff, size))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod sys {
+    use std::{ffi::CStr, io};
+
+    use libc::{c_char, c_void, getxattr, listxattr, XATTR_NOFOLLOW};
+
+    use super::check;
+
+    pub unsafe fn list(path: &CStr, buff: *mut c_char, size: usize) -> io::Result<usize> {
+        check(listxattr(path.as_ptr(), buff, size, XATTR_NOFOLLOW))
+    }
+
+    pub unsafe fn get(
+        path: &CStr, name: &CStr, buff: *mut c_void, size: usize,
+    ) -> io::Result<usize> {
+        check(getxattr(path.as_ptr(), name.as_ptr(), buff, size, 0, XATTR_NOFOLLOW))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+mod sys {
+    use std::{ffi::CStr, io};
+
+    use libc::{c_char, c_void, ENOTSUP};
+
+    pub unsafe fn list(_path: &CStr, _buff: *mut c_char, _size: usize) -> io::Result<usize> {
+        Err(io::Error::from_raw_os_error(ENOTSUP))
+    }
+
+    pub unsafe fn get(
+        _path: &CStr, _name: &CStr, _buff: *mut c_void, _size: usize,
+    ) -> io::Result<usize> {
+        Err(io::Error::from_raw_os_error(ENOTSUP))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CString, io, os::unix::ffi::OsStrExt, path::Path};
+
+    use libc::ENOTSUP;
+
+    use super::*;
+    use crate::testutil::TempDir;
+
+    /// Sets the extended attribute `name` of the file at `path` to `value`, or returns `false`
+    /// if the file system doesn't support it, for the test to be skipped.
+    fn set(path: &Path, name: &str, value: &[u8]) -> bool {
+        let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        let name = CString::new(name).unwrap();
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let res = unsafe {
+            libc::lsetxattr(path.as_ptr(), name.as_ptr(), value.as_ptr().cast(), value.len(), 0)
+        };
+        #[cfg(target_os = "macos")]
+        let res = unsafe {
+            libc::setxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+                libc::XATTR_NOFOLLOW,
+            )
+        };
+        #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+        let res = {
+            let _ = (path, name, value);
+            return false;
+        };
+
+        if res == 0 {
+            return true;
+        }
+        let err = io::Error::last_os_error();
+        assert_eq!(Some(ENOTSUP), err.raw_os_error(), "cannot set the attribute: {}", err);
+        false
+    }
+
+    #[test]
+    fn set_attribute_is_read_back() {
+        let dir = TempDir::new("xattr").unwrap();
+        let path = dir.create_file("file", b"content").unwrap();
+        if !set(&path, "user.coreutils.test", b"val\xffue") {
+            return;
+        }
+
+        let names = list(&path).unwrap();
+        assert!(names.contains(&BString::from("user.coreutils.test")));
+        assert_eq!(b"val\xffue", get(&path, "user.coreutils.test").unwrap().as_slice());
+    }
+
+    #[test]
+    fn empty_value() {
+        let dir = TempDir::new("xattr-empty").unwrap();
+        let path = dir.create_file("file", b"").unwrap();
+        if !set(&path, "user.coreutils.empty", b"") {
+            return;
+        }
+
+        assert!(get(&path, "user.coreutils.empty").unwrap().is_empty());
+    }
+
+    #[test]
+    fn missing_attribute() {
+        let dir = TempDir::new("xattr-missing").unwrap();
+        let path = dir.create_file("file", b"").unwrap();
+
+        assert!(get(&path, "user.coreutils.missing").is_err());
+    }
+
+    #[test]
+    fn missing_file() {
+        let dir = TempDir::new("xattr-nofile").unwrap();
+        let path = dir.join("missing");
+
+        let err = list(&path).unwrap_err();
+        if err.raw_os_error() != Some(ENOTSUP) {
+            assert_eq!(io::ErrorKind::NotFound, err.kind());
+        }
+    }
+
+    #[test]
+    fn nul_separated_names() {
+        assert!(split_names(b"").is_empty());
+        assert_eq!(
+            vec![BString::from("user.a"), BString::from("security.b")],
+            split_names(b"user.a\0security.b\0")
+        );
+    }
+}
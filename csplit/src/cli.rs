@@ -1,20 +1,13 @@
-use clap::{
-    crate_authors, crate_description, crate_name, crate_version, App, AppSettings::ColoredHelp, Arg,
-};
+use clap::{crate_authors, crate_description, crate_name, crate_version, App, Arg};
+use coreutils_core::cli::base_app;
 
 pub(crate) fn create_app<'a, 'b>() -> App<'a, 'b> {
-    App::new(crate_name!())
+    base_app(crate_name!(), crate_description!())
         .version(crate_version!())
         .author(crate_authors!())
-        .about(crate_description!())
-        .help_message("Display help information.")
-        .version_message("Display version information.")
-        .help_short("?")
-        .settings(&[ColoredHelp])
         .arg(
             Arg::with_name("FILE")
-                .help("File to read, or '-' to read from standard input.")
-                .required(true),
+                .help("File to read, or '-' to read from standard input."),
         )
         .arg(
             Arg::with_name("PATTERN")
@@ -22,12 +15,15 @@ pub(crate) fn create_app<'a, 'b>() -> App<'a, 'b> {
                 .long_help("Patterns to use when splitting file.\n\n\
                     PATTERN can be any of:\n\t\
                     INTEGER            copy lines up to line number\n\t\
+                    ~INTEGER           copy lines up to INTEGER lines before the end of file\n\t\
                     /REGEXP/[OFFSET]   copy lines up to line matching REGEXP\n\t\
                     %REGEXP%[OFFSET]   skip lines up to line matching REGEXP\n\t\
                     {INTEGER}          repeat preceeding pattern INTEGER times\n\t\
                     {*}                repeat preceeding pattern indefinitely\n\n\
                     If an OFFSET is given it should be an integer, either positive or negative. \
-                    An offset without sign is assumed to be positive.")
+                    An offset without sign is assumed to be positive.\n\n\
+                    ~INTEGER is not in POSIX; as it needs the number of lines, the whole input \
+                    is read before splitting when it's used.")
                 .multiple(true),
         )
         .arg(
@@ -59,24 +55,36 @@ pub(crate) fn create_app<'a, 'b>() -> App<'a, 'b> {
                 .visible_alias("quiet")
                 .short("s"),
         )
-    // .arg(
-    //     Arg::with_name("elide-empty")
-    //         .help("Remove empty output files.")
-    //         .long("elide-empty-files")
-    //         .short("z"),
-    // )
-    // .arg(
-    //     Arg::with_name("suppress")
-    //         .help("Suppress lines that match a PATTERN.")
-    //         .long("suppress-matched")
-    //         .short("x"),
-    // )
-    // .arg(
-    //     Arg::with_name("suffix-format")
-    //         .help("Format to use for the file suffix")
-    //         .long("suffix-format")
-    //         .short("b")
-    //         .value_name("FORMAT")
-    //         .default_value("%02d")
-    // )
+        .arg(
+            Arg::with_name("suffix-format")
+                .help("Use the printf FORMAT for output file name, instead of DIGITS.")
+                .long("suffix-format")
+                .short("b")
+                .value_name("FORMAT"),
+        )
+        .arg(
+            Arg::with_name("elide-empty")
+                .help("Remove empty output files.")
+                .long("elide-empty-files")
+                .short("z"),
+        )
+        .arg(
+            Arg::with_name("suppress")
+                .help("Suppress lines that match a PATTERN.")
+                .long("suppress-matched")
+                .short("x"),
+        )
+        .arg(
+            Arg::with_name("create-dirs")
+                .help("Create the directory of PREFIX and its parents if they do not exist.")
+                .long("create-dirs"),
+        )
+        .arg(
+            Arg::with_name("stats")
+                .help(
+                    "Print the number of files created and the total bytes written to standard \
+                     error when done.",
+                )
+                .long("stats"),
+        )
 }
@@ -1,107 +1,224 @@
 use std::{
     fmt,
-    fs::{remove_file, File},
-    io::{self, stdin, BufRead, BufReader, Read, Write},
+    fs::{self, remove_file, File},
+    io::{self, stdin, BufRead, BufReader, BufWriter, Cursor, Read, Write},
+    iter::Peekable,
+    path::Path,
     process, result,
+    str::Chars,
 };
 
 use clap::ArgMatches;
-use regex::Regex;
+use regex::bytes::Regex;
 
 mod cli;
 
 fn main() {
     let matches = cli::create_app().get_matches();
 
-    let mut created = Vec::new();
+    let result = run(&matches, &mut io::stdout(), &mut io::stderr());
+    process::exit(exit_status(&result));
+}
 
-    match csplit(&matches, &mut created) {
-        Ok(_) => (),
+/// Return the exit status for the `result` of a run, reporting the error if any.
+fn exit_status(result: &Result<()>) -> i32 {
+    match result {
+        Ok(()) => 0,
         Err(err) => {
             eprintln!("csplit: {}", err);
-            if !matches.is_present("keep") {
-                for fname in created {
-                    match remove_file(&fname) {
-                        Ok(_) => (),
-                        Err(err) => eprintln!("csplit: remove file {} failed: {}", fname, err),
-                    }
-                }
+            if err.is_usage() {
+                eprintln!("Try 'csplit --help' for more information.");
             }
-            process::exit(1);
+            1
         },
-    };
+    }
+}
+
+/// Split the input as asked by `args`, reporting the size of each file to `out`, and the
+/// summary of `--stats` to `err`.
+///
+/// On error, the created files are removed unless asked to keep them.
+fn run(args: &ArgMatches, out: &mut dyn Write, err: &mut dyn Write) -> Result<()> {
+    let mut created = Vec::new();
+
+    let result = csplit(args, &mut created, out, err);
+    if result.is_err() && !args.is_present("keep") {
+        remove_created(&created, err);
+    }
+    result
+}
+
+/// Remove the `created` files, the last one first, reporting to `err` the ones that can't be.
+fn remove_created(created: &[String], err: &mut dyn Write) {
+    for fname in created.iter().rev() {
+        if let Err(rm_err) = remove_file(fname) {
+            // Nothing else can be done if the report can't be written either
+            let _ = writeln!(err, "csplit: {}", Error::RemoveFailed(fname.clone(), rm_err));
+        }
+    }
 }
 
-fn csplit(args: &ArgMatches, created: &mut Vec<String>) -> Result<()> {
+fn csplit(
+    args: &ArgMatches, created: &mut Vec<String>, out: &mut dyn Write, err: &mut dyn Write,
+) -> Result<()> {
     // Ok to unwrap because it has default value
     let prefix = args.value_of("prefix").unwrap();
     let silent = args.is_present("silent");
-    // Ok to unwrap because it is required
-    let filename = args.value_of("FILE").unwrap();
+    let elide_empty = args.is_present("elide-empty");
+    let suppress_matched = args.is_present("suppress");
+    let stats = args.is_present("stats") && !silent;
+    let filename = args.value_of("FILE").ok_or(Error::NoOperand)?;
+    let patterns = match args.values_of("PATTERN") {
+        Some(patterns) => patterns.collect::<Vec<_>>(),
+        None => return Err(Error::MissingOperand(filename.to_string())),
+    };
     // Ok to unwrap because it has default value
-    let digits = parse_number(args.value_of("digits").unwrap())?;
-    let mut reader = open_input(filename)?;
-    let mut filer = Filer::new(prefix, digits, created, silent)?;
-
-    if let Some(patterns) = args.values_of("PATTERN") {
-        let mut patterns = build_patterns(patterns.collect::<Vec<_>>())?;
-        patterns.push(Pattern::new(Box::new(NeverMatcher::new())));
-        patterns.reverse();
-
-        // We know that there is at least one pattern in the list so
-        // we can use unwrap.
-        let mut pattern = patterns.pop().unwrap();
-        let mut lineno = 0;
-        loop {
-            let mut buffer = String::new();
-            match reader.read_line(&mut buffer) {
-                Ok(0) => break,
-                Ok(_) => (),
-                Err(err) => return Err(Error::ReadFailed(filename.to_string(), err)),
-            }
+    let digits = args.value_of("digits").unwrap();
+    let digits = digits.parse().map_err(|_| Error::InvalidNumber(digits.to_string()))?;
+    // The format of the suffix overrides the number of digits
+    let suffix = match args.value_of("suffix-format") {
+        Some(format) => Suffix::Format(SuffixFormat::parse(format)?),
+        None => Suffix::Digits(digits),
+    };
+    let mut reader = open_input(filename).map_err(|err| check_order(err, filename, &patterns))?;
+    if args.is_present("create-dirs") {
+        create_prefix_dir(prefix)?;
+    }
+    let mut filer = Filer::new(prefix, suffix, created, out, silent)?;
+    filer.elide_empty = elide_empty;
+    filer.suppress_matched = suppress_matched;
 
-            lineno += 1;
+    // Line numbers counted from the end need the number of lines, so the whole input is read
+    // first
+    let line_count = if patterns.iter().any(|pattern| pattern.starts_with('~')) {
+        let mut input = Vec::new();
+        reader
+            .read_to_end(&mut input)
+            .map_err(|err| Error::ReadFailed(filename.to_string(), err))?;
+        let count = line_count(&input);
+        reader = Box::new(Cursor::new(input));
+        Some(count)
+    } else {
+        None
+    };
+    let patterns = build_patterns(patterns, line_count)?;
+    split_lines(&mut reader, filename, patterns, &mut filer)?;
 
-            let (rotate_file, rotate_pattern) = pattern.match_line(&mut filer, lineno, &buffer);
+    if stats {
+        writeln!(err, "csplit: {} files created, {} bytes written", filer.files, filer.total_bytes)
+            .map_err(|err| Error::WriteFailed("standard error".to_string(), err))?;
+    }
+    Ok(())
+}
 
-            if rotate_pattern {
-                // We know that there is at least one more pattern
-                // in the list so we can use unwrap.
-                pattern = patterns.pop().unwrap();
-            }
+/// Replace the error opening the input `filename` by a hint about the order of the arguments,
+/// if `filename` looks like a pattern and the last of the `patterns` is an existing file, as
+/// in `csplit /re/ file`.
+fn check_order(err: Error, filename: &str, patterns: &[&str]) -> Error {
+    // Any number of lines is fine, it's only checked that `filename` parses as a pattern
+    let is_pattern = || build_patterns(vec![filename], Some(usize::MAX)).is_ok();
+    match (&err, patterns.last()) {
+        (Error::OpenFailed(_, io_err), Some(last))
+            if io_err.kind() == io::ErrorKind::NotFound
+                && is_pattern()
+                && Path::new(last).is_file() =>
+        {
+            Error::PatternsBeforeFile(last.to_string())
+        },
+        _ => err,
+    }
+}
 
-            if rotate_file {
-                filer.rotate()?;
-                let lines: Vec<_> = filer.buffer.drain(..).collect();
-                for line in lines {
-                    filer.write_line(&line)?;
-                }
-            }
+/// Create the directory of the output files named `prefix`, and its missing parents.
+///
+/// The directory is the part of `prefix` up to its last `/`, so that with `out/` the files
+/// are named by their number in `out`.
+fn create_prefix_dir(prefix: &str) -> Result<()> {
+    match prefix.rfind('/') {
+        Some(end) if end > 0 => {
+            let dir = &prefix[..end];
+            fs::create_dir_all(dir).map_err(|err| Error::CreateDirFailed(dir.to_string(), err))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Split the lines of `reader` with the `patterns`, writing the sections with `filer`.
+///
+/// The input is read and written one line at a time, so only the lines
+/// held back for a negative offset are kept in memory, unless a line
+/// number is counted from the end.
+fn split_lines(
+    reader: &mut dyn BufRead, filename: &str, mut patterns: Vec<Pattern>, filer: &mut Filer,
+) -> Result<()> {
+    patterns.push(Pattern::new(Box::new(NeverMatcher::new())));
+    patterns.reverse();
 
-            pattern.process_line(&mut filer, lineno, &buffer)?;
+    // We know that there is at least one pattern in the list so
+    // we can use unwrap.
+    let mut pattern = patterns.pop().unwrap();
+    let mut lineno = 0;
+    // Lines are bytes, so that any input is split, and written back as it is
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => break,
+            Ok(_) => (),
+            Err(err) => return Err(Error::ReadFailed(filename.to_string(), err)),
         }
-        filer.flush();
-        Ok(())
-    } else {
-        Err(Error::MissingOperand(filename.to_string()))
+
+        lineno += 1;
+
+        if pattern.match_line(filer, lineno, &buffer)? {
+            // We know that there is at least one more pattern
+            // in the list so we can use unwrap.
+            pattern = patterns.pop().unwrap();
+        }
+
+        pattern.process_line(filer, lineno, &buffer)?;
+    }
+
+    // A pattern can match at the end of the input when its offset moves
+    // the split point past it, then the next pattern is finished too
+    let mut result = pattern.finish(filer);
+    while let Ok(true) = result {
+        // The last pattern never matches, so there is one more
+        pattern = patterns.pop().unwrap();
+        result = pattern.finish(filer);
     }
+
+    // The sizes are reported even if the last pattern did not match, as the files are
+    // already written
+    let flushed = filer.flush();
+    result.and(flushed)
 }
 
-fn open_input(filename: &str) -> Result<BufReader<Box<dyn Read>>> {
-    let input: Box<dyn Read> = if filename == "-" {
-        Box::new(stdin())
-    } else {
-        Box::new(File::open(filename).map_err(|err| Error::OpenFailed(filename.to_string(), err))?)
-    };
+/// Open the input file, or the standard input if `filename` is `-`.
+fn open_input(filename: &str) -> Result<Box<dyn BufRead>> {
+    if filename == "-" {
+        return Ok(Box::new(BufReader::new(stdin())));
+    }
 
-    Ok(BufReader::new(input))
+    let file = File::open(filename).map_err(|err| Error::OpenFailed(filename.to_string(), err))?;
+    Ok(Box::new(BufReader::new(file)))
 }
 
-fn build_patterns(patterns: Vec<&str>) -> Result<Vec<Pattern>> {
+/// Build the patterns from their arguments.
+///
+/// `line_count` is the number of lines of the input, that is only known if it was read
+/// before splitting, and is needed by the line numbers counted from the end.
+fn build_patterns(patterns: Vec<&str>, line_count: Option<usize>) -> Result<Vec<Pattern>> {
     let mut result = Vec::new();
     for pattern in patterns {
         match pattern.chars().clone().next() {
             Some('0'..='9') => result.push(Pattern::new(LineMatcher::parse(&pattern)?)),
+            Some('~') => match line_count {
+                Some(count) => {
+                    result.push(Pattern::new(LineMatcher::parse_from_end(pattern, count)?))
+                },
+                None => return Err(Error::InvalidPattern(pattern.to_string())),
+            },
             Some(ch @ '/') | Some(ch @ '%') => {
                 result.push(Pattern::new(RegexMatcher::parse(&pattern, ch)?))
             },
@@ -125,17 +242,27 @@ fn build_patterns(patterns: Vec<&str>) -> Result<Vec<Pattern>> {
 /// Output files handler.
 ///
 /// The filer will rotate the files on request, write lines to the
-/// current file, and keep track of created output files.
+/// current file, and keep track of created output files. Unless silent,
+/// the size of each file is written to `out` once it's done.
 struct Filer<'a> {
     silent: bool,
+    elide_empty: bool,
+    /// Drop the line at each split point, that is the first line written
+    /// after it.
+    suppress_matched: bool,
+    suppress_next: bool,
+    out: &'a mut dyn Write,
     prefix: &'a str,
-    digits: i32,
-    file_counter: i32,
+    suffix: Suffix,
+    file_counter: usize,
     created: &'a mut Vec<String>,
-    writer: File,
+    writer: BufWriter<File>,
     bytes: usize,
+    /// Number of files written and kept so far, and their total size.
+    files: usize,
+    total_bytes: usize,
     current: String,
-    pub buffer: Vec<String>,
+    pub buffer: Vec<Vec<u8>>,
 }
 
 fn create_file(current: &str, created: &mut Vec<String>) -> Result<File> {
@@ -150,57 +277,277 @@ fn create_file(current: &str, created: &mut Vec<String>) -> Result<File> {
 
 impl<'a> Filer<'a> {
     fn new(
-        prefix: &'a str, digits: i32, created: &'a mut Vec<String>, silent: bool,
+        prefix: &'a str, suffix: Suffix, created: &'a mut Vec<String>, out: &'a mut dyn Write,
+        silent: bool,
     ) -> Result<Filer<'a>> {
-        let current = format!("{0}{2:01$}", prefix, digits as usize, 0);
-        let writer = create_file(&current, created)?;
+        let current = format!("{}{}", prefix, suffix.format(0));
+        let writer = BufWriter::new(create_file(&current, created)?);
         Ok(Filer {
             current,
             writer,
             prefix,
-            digits,
+            suffix,
             created,
+            out,
             silent,
+            elide_empty: false,
+            suppress_matched: false,
+            suppress_next: false,
             bytes: 0,
+            files: 0,
+            total_bytes: 0,
             file_counter: 1,
             buffer: Vec::new(),
         })
     }
 
     fn rotate(&mut self) -> Result<()> {
-        self.current = format!("{0}{2:01$}", self.prefix, self.digits as usize, self.file_counter);
-        if !self.silent {
-            println!("{}", self.bytes);
+        // An empty file is not kept, so it's used for the next section
+        if self.elide_empty && self.bytes == 0 {
+            return Ok(());
         }
+
+        self.close_file()?;
+        self.current = format!("{}{}", self.prefix, self.suffix.format(self.file_counter));
         self.bytes = 0;
         self.file_counter += 1;
-        create_file(&self.current, self.created).map(|file| self.writer = file)
+        create_file(&self.current, self.created).map(|file| self.writer = BufWriter::new(file))
+    }
+
+    /// Write the lines held back for a negative offset to the current file.
+    fn write_buffer(&mut self) -> Result<()> {
+        let lines: Vec<_> = self.buffer.drain(..).collect();
+        for line in lines {
+            self.write_line(&line)?;
+        }
+        Ok(())
     }
 
-    fn write_line(&mut self, line: &str) -> Result<()> {
+    /// Mark the next line written as the one at a split point.
+    fn split_point(&mut self) {
+        self.suppress_next = self.suppress_matched;
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> Result<()> {
+        if self.suppress_next {
+            self.suppress_next = false;
+            return Ok(());
+        }
+
         self.bytes += line.len();
         self.writer
-            .write_all(line.as_bytes())
+            .write_all(line)
             .map_err(|err| Error::WriteFailed(self.current.clone(), err))
     }
 
-    fn flush(&mut self) {
-        if !self.silent {
-            println!("{}", self.bytes);
+    fn flush(&mut self) -> Result<()> {
+        if self.elide_empty && self.bytes == 0 {
+            self.created.pop();
+            return remove_file(&self.current)
+                .map_err(|err| Error::RemoveFailed(self.current.clone(), err));
+        }
+        self.close_file()
+    }
+
+    /// Finish writing the current file and account for it.
+    fn close_file(&mut self) -> Result<()> {
+        self.flush_file()?;
+        self.files += 1;
+        self.total_bytes += self.bytes;
+        self.report_size()
+    }
+
+    /// Write what is left in the buffer of the current file.
+    fn flush_file(&mut self) -> Result<()> {
+        self.writer.flush().map_err(|err| Error::WriteFailed(self.current.clone(), err))
+    }
+
+    /// Write the size of the current file, unless silent.
+    fn report_size(&mut self) -> Result<()> {
+        if self.silent {
+            return Ok(());
         }
+        writeln!(self.out, "{}", self.bytes)
+            .map_err(|err| Error::WriteFailed("standard output".to_string(), err))
+    }
+}
+
+/// Suffix of the output file names.
+enum Suffix {
+    /// File number padded with zeroes to a number of digits, from `-n`.
+    ///
+    /// Numbers that need more digits are not cut, so the names get wider
+    /// after `xx99` as with GNU csplit.
+    Digits(usize),
+    /// File number formatted as with `printf`, from `-b`.
+    Format(SuffixFormat),
+}
+
+impl Suffix {
+    fn format(&self, number: usize) -> String {
+        match self {
+            Suffix::Digits(digits) => format!("{:01$}", number, digits),
+            Suffix::Format(format) => format.format(number),
+        }
+    }
+}
+
+/// A `printf` format with a single integer conversion, like `%03d`.
+///
+/// The conversion can be any of `d`, `i`, `u`, `o`, `x` and `X`, with
+/// the flags `0`, `-`, `#`, `+` and space, a width and a precision.
+#[derive(Debug)]
+struct SuffixFormat {
+    before: String,
+    after: String,
+    conversion: char,
+    zero: bool,
+    left: bool,
+    alternate: bool,
+    sign: Option<char>,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl SuffixFormat {
+    fn parse(format: &str) -> Result<SuffixFormat> {
+        let mut chars = format.chars().peekable();
+
+        let mut before = String::new();
+        loop {
+            match chars.next() {
+                Some('%') if chars.peek() == Some(&'%') => {
+                    chars.next();
+                    before.push('%');
+                },
+                Some('%') => break,
+                Some(c) => before.push(c),
+                None => return Err(Error::MissingConversion),
+            }
+        }
+
+        let (mut zero, mut left, mut alternate, mut sign) = (false, false, false, None);
+        while let Some(&c) = chars.peek() {
+            match c {
+                '0' => zero = true,
+                '-' => left = true,
+                '#' => alternate = true,
+                '+' => sign = Some('+'),
+                ' ' if sign.is_none() => sign = Some(' '),
+                ' ' => (),
+                _ => break,
+            }
+            chars.next();
+        }
+
+        let width = parse_digits(&mut chars);
+        let precision = if chars.peek() == Some(&'.') {
+            chars.next();
+            Some(parse_digits(&mut chars))
+        } else {
+            None
+        };
+
+        let conversion = match chars.next() {
+            Some(c @ 'd') | Some(c @ 'i') | Some(c @ 'u') => c,
+            Some(c @ 'o') | Some(c @ 'x') | Some(c @ 'X') => c,
+            Some(c) => return Err(Error::InvalidConversion(c)),
+            None => return Err(Error::MissingConversion),
+        };
+
+        let mut after = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '%' if chars.peek() == Some(&'%') => {
+                    chars.next();
+                    after.push('%');
+                },
+                '%' => return Err(Error::TooManyConversions),
+                c => after.push(c),
+            }
+        }
+
+        Ok(SuffixFormat {
+            before,
+            after,
+            conversion,
+            zero,
+            left,
+            alternate,
+            sign,
+            width,
+            precision,
+        })
+    }
+
+    fn format(&self, number: usize) -> String {
+        let mut digits = match self.conversion {
+            'o' => format!("{:o}", number),
+            'x' => format!("{:x}", number),
+            'X' => format!("{:X}", number),
+            _ => number.to_string(),
+        };
+        match self.precision {
+            // As with `printf`, zero with a precision of zero has no digits
+            Some(0) if number == 0 => digits.clear(),
+            Some(precision) if digits.len() < precision => {
+                digits.insert_str(0, &"0".repeat(precision - digits.len()))
+            },
+            _ => (),
+        }
+
+        let lead = match self.conversion {
+            'o' if self.alternate && !digits.starts_with('0') => "0".to_string(),
+            'x' if self.alternate && number != 0 => "0x".to_string(),
+            'X' if self.alternate && number != 0 => "0X".to_string(),
+            'd' | 'i' => self.sign.map(String::from).unwrap_or_default(),
+            _ => String::new(),
+        };
+
+        let len = lead.len() + digits.len();
+        let padding = self.width.saturating_sub(len);
+        let number = if self.left {
+            format!("{}{}{}", lead, digits, " ".repeat(padding))
+        } else if self.zero && self.precision.is_none() {
+            format!("{}{}{}", lead, "0".repeat(padding), digits)
+        } else {
+            format!("{}{}{}", " ".repeat(padding), lead, digits)
+        };
+
+        format!("{}{}{}", self.before, number, self.after)
+    }
+}
+
+/// Parse the decimal digits at the start of `chars`, or return 0 if there is none.
+fn parse_digits(chars: &mut Peekable<Chars>) -> usize {
+    let mut value = 0usize;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        value = value.saturating_mul(10).saturating_add(digit as usize);
+        chars.next();
     }
+    value
 }
 
 #[derive(Debug)]
 enum Error {
     OutOfRange(usize, Option<i32>),
+    FromEndOutOfRange(String),
     CreateFailed(String, io::Error),
+    CreateDirFailed(String, io::Error),
     OpenFailed(String, io::Error),
     ReadFailed(String, io::Error),
     WriteFailed(String, io::Error),
+    RemoveFailed(String, io::Error),
     InvalidNumber(String),
     InvalidPattern(String),
+    NoOperand,
     MissingOperand(String),
+    PatternsBeforeFile(String),
+    NotFound(String, Option<i32>),
+    ZeroLineNumber(String),
+    MissingConversion,
+    TooManyConversions,
+    InvalidConversion(char),
 }
 
 impl fmt::Display for Error {
@@ -210,27 +557,55 @@ impl fmt::Display for Error {
             Error::OutOfRange(line, Some(rep)) => {
                 write!(f, "'{}': line number out of range on repetition {}", line, rep)
             },
+            Error::FromEndOutOfRange(pat) => write!(f, "'{}': line number out of range", pat),
             Error::CreateFailed(file, err) => {
                 write!(f, "cannot open '{}' for writing: {}", file, err)
             },
+            Error::CreateDirFailed(dir, err) => {
+                write!(f, "cannot create directory '{}': {}", dir, err)
+            },
             Error::OpenFailed(file, err) => {
                 write!(f, "cannot open '{}' for reading: {}", file, err)
             },
             Error::ReadFailed(file, err) => write!(f, "cannot read '{}': {}", file, err),
             Error::WriteFailed(file, err) => write!(f, "cannot write '{}': {}", file, err),
+            Error::RemoveFailed(file, err) => write!(f, "cannot remove '{}': {}", file, err),
             Error::InvalidNumber(val) => write!(f, "invalid number '{}'", val),
             Error::InvalidPattern(pat) => write!(f, "{}: invalid pattern", pat),
+            Error::NoOperand => write!(f, "missing operand"),
             Error::MissingOperand(arg) => write!(f, "missing operand after '{}'", arg),
+            Error::PatternsBeforeFile(file) => {
+                write!(f, "the input file '{}' must come before the patterns", file)
+            },
+            Error::NotFound(pat, None) => write!(f, "'{}': match not found", pat),
+            Error::NotFound(pat, Some(rep)) => {
+                write!(f, "'{}': match not found on repetition {}", pat, rep)
+            },
+            Error::ZeroLineNumber(pat) => {
+                write!(f, "{}: line number must be greater than zero", pat)
+            },
+            Error::MissingConversion => write!(f, "missing conversion specifier in suffix"),
+            Error::TooManyConversions => write!(f, "too many %-conversions in suffix"),
+            Error::InvalidConversion(c) => {
+                write!(f, "invalid conversion specifier in suffix: {}", c)
+            },
         }
     }
 }
 
+impl Error {
+    /// Whether the error is about how csplit was invoked.
+    fn is_usage(&self) -> bool {
+        matches!(self, Error::NoOperand | Error::MissingOperand(_) | Error::PatternsBeforeFile(_))
+    }
+}
+
 type Result<T> = result::Result<T, Error>;
 
 /// Pattern with a matcher and a repeat.
 struct Pattern {
-    /// Number of repetitions. Negative or zero repeat means infinite
-    /// repetition
+    /// Number of matches left, the first one and its repetitions.
+    /// Negative or zero repeat means infinite repetition
     repeat: i32,
 
     /// Count of number of matches.
@@ -241,33 +616,74 @@ struct Pattern {
 
 impl Pattern {
     fn new(matcher: Box<dyn Matcher>) -> Pattern {
-        Pattern { repeat: 1, count: 1, matcher }
+        Pattern { repeat: 1, count: 0, matcher }
+    }
+
+    /// Repetition the pattern is at, if it already matched.
+    fn repetition(&self) -> Option<i32> {
+        if self.count > 0 { Some(self.count) } else { None }
+    }
+
+    /// Add the repetition the pattern is at to a error of the matcher.
+    fn with_repetition(&self, err: Error) -> Error {
+        match err {
+            Error::OutOfRange(l, None) => Error::OutOfRange(l, self.repetition()),
+            Error::NotFound(pat, None) => Error::NotFound(pat, self.repetition()),
+            err => err,
+        }
     }
 
     /// Check if line matches.
     ///
-    /// Returns a pair of booleans. First boolean indicate the the
-    /// file should be rotated, second one if the pattern should be
-    /// rotated.
-    fn match_line(&mut self, _filer: &mut Filer, lineno: usize, line: &str) -> (bool, bool) {
+    /// On a match the file is rotated if the matcher asks for it, and
+    /// the lines held back for a negative offset are written to the
+    /// current file. Returns `true` if the pattern should be rotated.
+    fn match_line(&mut self, filer: &mut Filer, lineno: usize, line: &[u8]) -> Result<bool> {
         let (rotate_file, pattern_matched) = self.matcher.match_line(lineno, line);
 
-        if pattern_matched {
-            self.repeat -= 1;
-            self.count += 1;
-            (rotate_file, self.repeat == 0)
-        } else {
-            (false, false)
+        if !pattern_matched {
+            return Ok(false);
+        }
+
+        self.repeat -= 1;
+        self.count += 1;
+        if rotate_file {
+            filer.rotate()?;
         }
+        filer.split_point();
+        filer.write_buffer()?;
+        Ok(self.repeat == 0)
     }
 
     /// Process a line of input.
     ///
     /// Return `true` if the pattern is exhausted, `false` otherwise.
-    fn process_line(&mut self, filer: &mut Filer, lineno: usize, line: &str) -> Result<()> {
-        match self.matcher.process_line(filer, lineno, line) {
-            Err(Error::OutOfRange(l, None)) => Err(Error::OutOfRange(l, Some(self.count))),
-            err => err,
+    fn process_line(&mut self, filer: &mut Filer, lineno: usize, line: &[u8]) -> Result<()> {
+        self.matcher.process_line(filer, lineno, line).map_err(|err| self.with_repetition(err))
+    }
+
+    /// Finish the pattern at the end of the input.
+    ///
+    /// A regex repeated indefinitely is done when the input ends, any other pattern that is
+    /// still waiting for a line has failed. As with GNU csplit, this includes a line number
+    /// repeated indefinitely.
+    ///
+    /// Return `true` if the pattern matched at the end of the input and
+    /// the next pattern should be finished, `false` otherwise.
+    fn finish(&mut self, filer: &mut Filer) -> Result<bool> {
+        loop {
+            match self.matcher.finish(filer) {
+                Ok(true) => {
+                    self.repeat -= 1;
+                    self.count += 1;
+                    if self.repeat == 0 {
+                        return Ok(true);
+                    }
+                },
+                Ok(false) => return Ok(false),
+                Err(Error::NotFound(..)) if self.repeat <= 0 => return Ok(false),
+                Err(err) => return Err(self.with_repetition(err)),
+            }
         }
     }
 }
@@ -279,13 +695,20 @@ trait Matcher: fmt::Debug {
     /// Return a pair of booleans:
     /// - First boolean is `true` if the file rotation should take place.
     /// - Second boolean is `true` if the line matches.
-    fn match_line(&mut self, lineno: usize, line: &str) -> (bool, bool);
+    fn match_line(&mut self, lineno: usize, line: &[u8]) -> (bool, bool);
 
     /// Process an input line.
     ///
     /// Return `true` if the matcher matched the line, `false`
     /// otherwise.
-    fn process_line(&mut self, filer: &mut Filer, lineno: usize, line: &str) -> Result<()>;
+    fn process_line(&mut self, filer: &mut Filer, lineno: usize, line: &[u8]) -> Result<()>;
+
+    /// Finish matching when the input ends.
+    ///
+    /// Write any line still held back and return an error if the
+    /// matcher did not match. Return `true` if the matcher matched at
+    /// the end of the input, `false` if it has nothing to match.
+    fn finish(&mut self, filer: &mut Filer) -> Result<bool>;
 }
 
 /// Never match a line.
@@ -303,20 +726,28 @@ impl NeverMatcher {
 }
 
 impl Matcher for NeverMatcher {
-    fn match_line(&mut self, _lineno: usize, _line: &str) -> (bool, bool) {
+    fn match_line(&mut self, _lineno: usize, _line: &[u8]) -> (bool, bool) {
         (false, false)
     }
 
-    fn process_line(&mut self, filer: &mut Filer, _lineno: usize, line: &str) -> Result<()> {
+    fn process_line(&mut self, filer: &mut Filer, _lineno: usize, line: &[u8]) -> Result<()> {
         filer.write_line(line)
     }
+
+    fn finish(&mut self, _filer: &mut Filer) -> Result<bool> {
+        Ok(false)
+    }
 }
 
 /// Line matcher.
 ///
-/// Match a specific line count relative to the start of the section.
+/// Match a specific line number of the input, and every `step` lines
+/// after it when repeated.
 #[derive(Debug)]
 struct LineMatcher {
+    /// Line number given in the pattern.
+    step: usize,
+    /// Line to split before, moved by `step` on each repetition.
     lineno: usize,
 }
 
@@ -324,22 +755,54 @@ impl LineMatcher {
     fn parse(pattern: &str) -> Result<Box<dyn Matcher>> {
         let num =
             pattern.parse::<usize>().map_err(|_| Error::InvalidPattern(pattern.to_string()))?;
-        Ok(Box::new(LineMatcher { lineno: num }))
+        if num == 0 {
+            return Err(Error::ZeroLineNumber(pattern.to_string()));
+        }
+        Ok(Box::new(LineMatcher { step: num, lineno: num }))
+    }
+
+    /// Parse a `~N` pattern, that is not in GNU csplit, matching the line `N` lines before the
+    /// end of the `line_count` lines of the input.
+    ///
+    /// It's the same as the line number `line_count - N + 1`, so that the last `N` lines go to
+    /// the next file.
+    fn parse_from_end(pattern: &str, line_count: usize) -> Result<Box<dyn Matcher>> {
+        let num = pattern[1..]
+            .parse::<usize>()
+            .map_err(|_| Error::InvalidPattern(pattern.to_string()))?;
+        if num == 0 {
+            return Err(Error::ZeroLineNumber(pattern.to_string()));
+        }
+        if num > line_count {
+            return Err(Error::FromEndOutOfRange(pattern.to_string()));
+        }
+
+        let lineno = line_count - num + 1;
+        Ok(Box::new(LineMatcher { step: lineno, lineno }))
     }
 }
 
 impl Matcher for LineMatcher {
-    fn match_line(&mut self, lineno: usize, _line: &str) -> (bool, bool) {
-        if self.lineno == lineno { (true, true) } else { (false, false) }
+    fn match_line(&mut self, lineno: usize, _line: &[u8]) -> (bool, bool) {
+        if self.lineno == lineno {
+            self.lineno += self.step;
+            (true, true)
+        } else {
+            (false, false)
+        }
     }
 
-    fn process_line(&mut self, filer: &mut Filer, lineno: usize, line: &str) -> Result<()> {
+    fn process_line(&mut self, filer: &mut Filer, lineno: usize, line: &[u8]) -> Result<()> {
         if self.lineno < lineno {
-            Err(Error::OutOfRange(self.lineno, None))
+            Err(Error::OutOfRange(self.step, None))
         } else {
             filer.write_line(line)
         }
     }
+
+    fn finish(&mut self, _filer: &mut Filer) -> Result<bool> {
+        Err(Error::OutOfRange(self.step, None))
+    }
 }
 
 /// Regexp matcher.
@@ -347,6 +810,7 @@ impl Matcher for LineMatcher {
 /// Match a line if it matches the regular expression.
 #[derive(Debug)]
 struct RegexMatcher {
+    pattern: String,
     regex: Regex,
     skip: bool,
     offset: i32,
@@ -356,9 +820,9 @@ struct RegexMatcher {
 // /REGEXP/[OFFSET]
 // %REGEXP%[OFFSET]
 impl RegexMatcher {
-    fn new(regex: &str, skip: bool, offset: i32) -> Result<RegexMatcher> {
+    fn new(pattern: &str, regex: &str, skip: bool, offset: i32) -> Result<RegexMatcher> {
         let regex = Regex::new(regex).map_err(|_| Error::InvalidPattern(regex.to_string()))?;
-        Ok(RegexMatcher { skip, regex, offset, line_match: None })
+        Ok(RegexMatcher { pattern: pattern.to_string(), skip, regex, offset, line_match: None })
     }
 
     fn parse(pattern: &str, first: char) -> Result<Box<dyn Matcher>> {
@@ -373,17 +837,22 @@ impl RegexMatcher {
         let slice = &pattern[pat_end + 1..];
         let offset = if !slice.is_empty() { parse_number(slice)? } else { 0 };
 
-        Ok(Box::new(RegexMatcher::new(&pattern[1..pat_end], first == '%', offset)?))
+        Ok(Box::new(RegexMatcher::new(pattern, &pattern[1..pat_end], first == '%', offset)?))
     }
 }
 
 impl Matcher for RegexMatcher {
-    fn match_line(&mut self, lineno: usize, line: &str) -> (bool, bool) {
+    fn match_line(&mut self, lineno: usize, line: &[u8]) -> (bool, bool) {
         if let Some(the_line) = self.line_match {
+            // Start looking for the next match on a repetition
+            if the_line == lineno {
+                self.line_match = None;
+            }
             return (!self.skip, the_line == lineno);
         }
 
-        if self.regex.is_match(line) {
+        // The line terminator is not part of the line, so that `$` matches at its end
+        if self.regex.is_match(line_content(line)) {
             if self.offset > 0 {
                 self.line_match = Some(lineno + self.offset as usize);
                 (!self.skip, false)
@@ -395,14 +864,17 @@ impl Matcher for RegexMatcher {
         }
     }
 
-    fn process_line(&mut self, filer: &mut Filer, _lineno: usize, line: &str) -> Result<()> {
+    fn process_line(&mut self, filer: &mut Filer, _lineno: usize, line: &[u8]) -> Result<()> {
         if self.offset < 0 {
-            filer.buffer.push(line.to_string());
+            filer.buffer.push(line.to_vec());
             let count = filer.buffer.len() as i32 + self.offset;
             if count > 0 {
                 let lines: Vec<_> = filer.buffer.drain(0..count as usize).collect();
-                for line in lines {
-                    filer.write_line(&line)?;
+                // Skipped lines are dropped, only the ones before the match are kept
+                if !self.skip {
+                    for line in lines {
+                        filer.write_line(&line)?;
+                    }
                 }
             }
         } else if !self.skip {
@@ -410,33 +882,73 @@ impl Matcher for RegexMatcher {
         }
         Ok(())
     }
+
+    fn finish(&mut self, filer: &mut Filer) -> Result<bool> {
+        if self.skip {
+            filer.buffer.clear();
+        } else {
+            filer.write_buffer()?;
+        }
+
+        // The line matched but the offset moves the split point past the
+        // end of the input, so it is split at the end
+        if self.line_match.take().is_some() {
+            if !self.skip {
+                filer.rotate()?;
+            }
+            return Ok(true);
+        }
+        Err(Error::NotFound(self.pattern.clone(), None))
+    }
+}
+
+/// Return the `line` without its terminator, either `\n` or `\r\n`.
+fn line_content(line: &[u8]) -> &[u8] {
+    if line.ends_with(b"\r\n") {
+        &line[..line.len() - 2]
+    } else if line.ends_with(b"\n") {
+        &line[..line.len() - 1]
+    } else {
+        line
+    }
+}
+
+/// Return the number of lines of `input`, where the last one may have no terminator.
+fn line_count(input: &[u8]) -> usize {
+    let terminated = input.iter().filter(|&&b| b == b'\n').count();
+    if matches!(input.last(), Some(&b) if b != b'\n') { terminated + 1 } else { terminated }
 }
 
 // {INTEGER}
 // {*}
+//
+// Return the number of matches of the preceding pattern, which is
+// matched once and then repeated INTEGER times, or -1 for `{*}`.
 fn parse_repeat(pattern: &str) -> Result<i32> {
     if pattern == "{*}" {
-        Ok(-1)
-    } else {
-        let len = pattern.len() - 1;
-        match &pattern[1..len].parse() {
-            Ok(value) => Ok(*value),
-            Err(_) => Err(Error::InvalidPattern(pattern.to_string())),
-        }
+        return Ok(-1);
     }
+
+    pattern
+        .strip_prefix('{')
+        .and_then(|pat| pat.strip_suffix('}'))
+        .and_then(|count| count.parse::<i32>().ok())
+        .filter(|&count| count >= 0)
+        .and_then(|count| count.checked_add(1))
+        .ok_or_else(|| Error::InvalidPattern(pattern.to_string()))
 }
 
 /// Parse a string slice as a number, or return error.
 fn parse_number(slice: &str) -> Result<i32> {
-    if slice.is_empty() {
-        slice.parse().map_err(|_| Error::InvalidNumber(slice.to_string()))
-    } else {
-        Ok(0)
-    }
+    slice.parse().map_err(|_| Error::InvalidNumber(slice.to_string()))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{cell::Cell, fs, io::Read};
+
+    use coreutils_core::testutil::TempDir;
+
     use super::*;
 
     // Macro to assert that an expression matches a pattern.
@@ -472,4 +984,804 @@ mod tests {
         assert_matches!(RegexMatcher::parse("/foo/1", '/'), Ok(_));
         assert_matches!(RegexMatcher::parse("/foo/-1", '/'), Ok(_));
     }
+
+    /// Split `input` with the `args` into a new temporary directory named after `name`.
+    ///
+    /// Return the result of the split with the names and contents of the created files.
+    fn split(name: &str, input: &str, args: &[&str]) -> (Result<()>, Vec<(String, String)>) {
+        split_with_prefix(name, "xx", input, args)
+    }
+
+    /// Same as [`split`], with the `prefix` of the output files.
+    fn split_with_prefix(
+        name: &str, prefix: &str, input: &str, args: &[&str],
+    ) -> (Result<()>, Vec<(String, String)>) {
+        let dir = test_dir(name, input);
+        let matches = test_args(dir.path(), prefix, args);
+
+        let mut created = Vec::new();
+        let result = csplit(&matches, &mut created, &mut io::sink(), &mut io::sink());
+        let files = created
+            .iter()
+            .map(|file| {
+                let name = file.rsplit('/').next().unwrap().to_string();
+                (name, fs::read_to_string(file).unwrap())
+            })
+            .collect();
+
+        (result, files)
+    }
+
+    /// Create a new temporary directory named after `name`, with a file `input` holding
+    /// `input`, that is removed when dropped.
+    fn test_dir(name: &str, input: impl AsRef<[u8]>) -> TempDir {
+        let dir = TempDir::new(&format!("csplit-{}", name)).unwrap();
+        dir.create_file("input", input.as_ref()).unwrap();
+        dir
+    }
+
+    /// Parse the `args` to split the input of `dir` into files named `prefix` in `dir`.
+    fn test_args<'a>(dir: &Path, prefix: &str, args: &[&'a str]) -> ArgMatches<'a> {
+        let input = dir.join("input").to_str().unwrap().to_string();
+        let prefix = dir.join(prefix).to_str().unwrap().to_string();
+        let mut argv = vec!["csplit".to_string(), input, "-f".to_string(), prefix];
+        argv.extend(args.iter().map(|arg| arg.to_string()));
+        cli::create_app().get_matches_from(argv)
+    }
+
+    /// Return the contents of the output `files`.
+    fn contents(files: &[(String, String)]) -> Vec<&str> {
+        files.iter().map(|(_, content)| content.as_str()).collect()
+    }
+
+    #[test]
+    fn split_on_regex() {
+        let (result, files) = split("regex", "one\ntwo\n---\nthree\n", &["/---/"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["xx00", "xx01"], files.iter().map(|(n, _)| n).collect::<Vec<_>>());
+        assert_eq!(vec!["one\ntwo\n", "---\nthree\n"], contents(&files));
+    }
+
+    #[test]
+    fn split_on_several_regex() {
+        let input = "a\n---\nb\n===\nc\n";
+        let (result, files) = split("several-regex", input, &["/---/", "/===/"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["a\n", "---\nb\n", "===\nc\n"], contents(&files));
+    }
+
+    #[test]
+    fn split_on_regex_at_line_end() {
+        let (result, files) = split("regex-end", "done\nfoo\nbar\n", &["/o$/"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["done\n", "foo\nbar\n"], contents(&files));
+    }
+
+    #[test]
+    fn regex_not_found() {
+        let (result, files) = split("regex-not-found", "one\ntwo\n", &["/zz/"]);
+        assert_matches!(result, Err(Error::NotFound(ref pat, None)) if pat == "/zz/");
+        assert_eq!(vec!["one\ntwo\n"], contents(&files));
+        assert_eq!("'/zz/': match not found", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn skip_log_header() {
+        let input = "# header\n# more\nSTART\nentry 1\nentry 2\n";
+        let (result, files) = split("skip-header", input, &["%^START%"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["xx00"], files.iter().map(|(n, _)| n).collect::<Vec<_>>());
+        assert_eq!(vec!["START\nentry 1\nentry 2\n"], contents(&files));
+    }
+
+    #[test]
+    fn skip_keeps_numbering() {
+        let input = "a\n---\nb\n===\nc\n---\nd\n";
+        let (result, files) = split("skip-numbering", input, &["/---/", "%===%", "/---/"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["xx00", "xx01", "xx02"], files.iter().map(|(n, _)| n).collect::<Vec<_>>());
+        assert_eq!(vec!["a\n", "===\nc\n", "---\nd\n"], contents(&files));
+    }
+
+    #[test]
+    fn skip_with_negative_offset() {
+        let input = "h1\nh2\n===\nbody\n";
+        let (result, files) = split("skip-offset", input, &["%===%-1"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["h2\n===\nbody\n"], contents(&files));
+    }
+
+    #[test]
+    fn skip_not_found() {
+        let (result, files) = split("skip-not-found", "one\ntwo\n", &["%zz%"]);
+        assert_matches!(result, Err(Error::NotFound(ref pat, None)) if pat == "%zz%");
+        assert_eq!(vec![""], contents(&files));
+    }
+
+    #[test]
+    fn repeat_count() {
+        assert_matches!(parse_repeat("{*}"), Ok(-1));
+        assert_matches!(parse_repeat("{0}"), Ok(1));
+        assert_matches!(parse_repeat("{3}"), Ok(4));
+        assert_matches!(parse_repeat("{-1}"), Err(Error::InvalidPattern(_)));
+        assert_matches!(parse_repeat("{x}"), Err(Error::InvalidPattern(_)));
+        assert_matches!(parse_repeat("{2"), Err(Error::InvalidPattern(_)));
+    }
+
+    #[test]
+    fn repeat_regex() {
+        let input = "a\n--\nb\n--\nc\n--\nd\n--\ne\n";
+        let (result, files) = split("repeat", input, &["/--/", "{2}"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["a\n", "--\nb\n", "--\nc\n", "--\nd\n--\ne\n"], contents(&files));
+    }
+
+    #[test]
+    fn repeat_regex_not_found() {
+        let (result, files) = split("repeat-not-found", "a\n--\nb\n", &["/--/", "{2}"]);
+        assert_matches!(result, Err(Error::NotFound(_, Some(1))));
+        assert_eq!(vec!["a\n", "--\nb\n"], contents(&files));
+        assert_eq!("'/--/': match not found on repetition 1", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn repeat_regex_until_end() {
+        let input = "a\n--\nb\n--\nc\n";
+        let (result, files) = split("repeat-forever", input, &["/--/", "{*}"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["a\n", "--\nb\n", "--\nc\n"], contents(&files));
+
+        let (result, files) = split("repeat-forever-none", "a\nb\n", &["/--/", "{*}"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["a\nb\n"], contents(&files));
+    }
+
+    #[test]
+    fn repeat_regex_with_offset() {
+        let input = "a\n--\nb\n--\nc\n";
+        let (result, files) = split("repeat-offset", input, &["/--/+1", "{*}"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["a\n--\n", "b\n--\n", "c\n"], contents(&files));
+    }
+
+    /// Return the numbers from 1 to `count`, one per line.
+    fn numbered_lines(count: usize) -> String {
+        (1..=count).map(|n| format!("{}\n", n)).collect()
+    }
+
+    /// Return the sizes of the output `files`.
+    fn sizes(files: &[(String, String)]) -> Vec<usize> {
+        files.iter().map(|(_, content)| content.len()).collect()
+    }
+
+    #[test]
+    fn crlf_lines_are_kept() {
+        let input = "a\r\nb\r\nc\r\n\r\nd\r\ne";
+        let (result, files) = split("crlf", input, &["/^b$/", "/^$/"]);
+        assert_matches!(result, Ok(()));
+
+        assert_eq!(vec!["a\r\n", "b\r\nc\r\n", "\r\nd\r\ne"], contents(&files));
+        assert_eq!(input, contents(&files).concat());
+    }
+
+    #[test]
+    fn line_terminators() {
+        assert_eq!(b"a", line_content(b"a\n"));
+        assert_eq!(b"a", line_content(b"a\r\n"));
+        assert_eq!(b"a\r", line_content(b"a\r"));
+        assert_eq!(b"a\r", line_content(b"a\r\r\n"));
+        assert_eq!(b"a", line_content(b"a"));
+        assert_eq!(b"caf\xe9", line_content(b"caf\xe9\r\n"));
+    }
+
+    #[test]
+    fn count_lines() {
+        assert_eq!(0, line_count(b""));
+        assert_eq!(1, line_count(b"a"));
+        assert_eq!(1, line_count(b"a\n"));
+        assert_eq!(2, line_count(b"a\n\xffb"));
+        assert_eq!(2, line_count(b"\n\n"));
+    }
+
+    #[test]
+    fn split_non_utf8_input() {
+        let input = b"caf\xe9\r\n--\r\nna\xefve\r\nend";
+
+        let cases: [(&str, &str, &[u8]); 2] = [
+            ("non-utf8", "/^--$/", b"caf\xe9\r\n"),
+            ("non-utf8-from-end", "~2", b"caf\xe9\r\n--\r\n"),
+        ];
+        for (name, pattern, expected) in &cases {
+            let dir = test_dir(name, &input[..]);
+            let matches = test_args(dir.path(), "xx", &[pattern]);
+            run(&matches, &mut io::sink(), &mut io::sink()).unwrap();
+
+            let first = fs::read(dir.join("xx00")).unwrap();
+            let second = fs::read(dir.join("xx01")).unwrap();
+            assert_eq!(*expected, &first[..], "{}", pattern);
+            // The files are the input split, byte for byte
+            assert_eq!(input.to_vec(), [first, second].concat(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn split_on_line_numbers() {
+        let (result, files) = split("lines", &numbered_lines(30), &["10", "20"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec![18, 30, 33], sizes(&files));
+        assert!(files[1].1.starts_with("10\n") && files[2].1.starts_with("20\n"));
+    }
+
+    #[test]
+    fn repeat_line_number() {
+        let (result, files) = split("lines-repeat", &numbered_lines(30), &["10", "{1}"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec![18, 30, 33], sizes(&files));
+
+        // Line numbers past the end of the input are an error, even when repeated indefinitely
+        let (result, files) = split("lines-repeat-forever", &numbered_lines(30), &["10", "{*}"]);
+        assert_matches!(result, Err(Error::OutOfRange(10, Some(3))));
+        assert_eq!(vec![18, 30, 30, 3], sizes(&files));
+    }
+
+    #[test]
+    fn line_numbers_and_regex() {
+        let (result, files) = split("lines-regex", &numbered_lines(30), &["/^5$/", "12", "/0$/"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!("1\n2\n3\n4\n", files[0].1);
+        assert_eq!("5\n6\n7\n8\n9\n10\n11\n", files[1].1);
+        assert_eq!("12\n13\n14\n15\n16\n17\n18\n19\n", files[2].1);
+        assert!(files[3].1.starts_with("20\n"));
+    }
+
+    #[test]
+    fn line_number_out_of_range() {
+        let (result, files) = split("lines-out-of-range", &numbered_lines(5), &["10"]);
+        assert_matches!(result, Err(Error::OutOfRange(10, None)));
+        assert_eq!(vec![10], sizes(&files));
+
+        let (result, _) = split("lines-out-of-range-repeat", &numbered_lines(5), &["2", "{2}"]);
+        assert_matches!(result, Err(Error::OutOfRange(2, Some(2))));
+    }
+
+    #[test]
+    fn line_number_from_end() {
+        let (result, files) = split("lines-from-end", &numbered_lines(30), &["~10"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(2, files.len());
+        assert!(files[0].1.ends_with("\n20\n"));
+        assert_eq!(10, files[1].1.lines().count());
+        assert!(files[1].1.starts_with("21\n") && files[1].1.ends_with("\n30\n"));
+
+        let (result, files) = split("lines-from-end-regex", &numbered_lines(30), &["/^5$/", "~3"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec![8, 64, 9], sizes(&files));
+    }
+
+    #[test]
+    fn line_number_from_end_out_of_range() {
+        let (result, _) = split("lines-from-end-out-of-range", &numbered_lines(30), &["~100"]);
+        assert_matches!(result, Err(Error::FromEndOutOfRange(ref pat)) if pat == "~100");
+        assert_eq!(
+            "'~100': line number out of range",
+            Error::FromEndOutOfRange("~100".to_string()).to_string()
+        );
+
+        assert_matches!(LineMatcher::parse_from_end("~0", 30), Err(Error::ZeroLineNumber(_)));
+        assert_matches!(LineMatcher::parse_from_end("~x", 30), Err(Error::InvalidPattern(_)));
+        assert_matches!(build_patterns(vec!["~1"], None).err(), Some(Error::InvalidPattern(_)));
+    }
+
+    #[test]
+    fn line_number_zero() {
+        assert_matches!(LineMatcher::parse("0"), Err(Error::ZeroLineNumber(_)));
+        assert_matches!(LineMatcher::parse("1"), Ok(_));
+    }
+
+    #[test]
+    fn regex_positive_offset() {
+        let (result, files) = split("offset-plus", "a\n--\nb\nc\n", &["/--/+1"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["a\n--\n", "b\nc\n"], contents(&files));
+    }
+
+    #[test]
+    fn regex_negative_offset() {
+        let (result, files) = split("offset-minus", "a\nb\n--\nc\n", &["/--/-1"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["a\n", "b\n--\nc\n"], contents(&files));
+    }
+
+    #[test]
+    fn regex_offset_clamped_at_start() {
+        let (result, files) = split("offset-start", "a\n--\nb\n", &["/--/-5"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["", "a\n--\nb\n"], contents(&files));
+    }
+
+    #[test]
+    fn regex_offset_clamped_at_end() {
+        let (result, files) = split("offset-end", "a\n--\nb\n", &["/--/+5"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["a\n--\nb\n", ""], contents(&files));
+
+        // The next pattern gets no input
+        let (result, _) = split("offset-end-next", "a\n--\nb\n", &["/--/+5", "/b/"]);
+        assert_matches!(result, Err(Error::NotFound(ref pat, None)) if pat == "/b/");
+
+        let (result, files) = split("offset-end-skip", "a\n--\nb\n", &["/a/", "%--%+5"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["", ""], contents(&files));
+    }
+
+    /// Return the names of the output `files`.
+    fn names(files: &[(String, String)]) -> Vec<&str> {
+        files.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn custom_prefix() {
+        let (result, files) = split_with_prefix("prefix", "part-", "a\nb\n", &["2"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["part-00", "part-01"], names(&files));
+    }
+
+    #[test]
+    fn suffix_widens_past_digits() {
+        let (result, files) = split("widen", &numbered_lines(101), &["/./", "{99}"]);
+        assert_matches!(result, Ok(()));
+
+        let names = names(&files);
+        assert_eq!(101, names.len());
+        assert_eq!(["xx00", "xx01"], names[..2]);
+        assert_eq!(["xx98", "xx99", "xx100"], names[98..]);
+        assert_eq!("99\n", files[99].1);
+        assert_eq!("100\n101\n", files[100].1);
+    }
+
+    #[test]
+    fn suffix_format_widens_past_width() {
+        let args = ["-b", "%02d", "/./", "{99}"];
+        let (result, files) = split("widen-format", &numbered_lines(101), &args);
+        assert_matches!(result, Ok(()));
+        assert_eq!(["xx99", "xx100"], names(&files)[99..]);
+    }
+
+    #[test]
+    fn suffix_format() {
+        let (result, files) = split("suffix", "a\nb\n", &["-b", "%03d", "2"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["xx000", "xx001"], names(&files));
+
+        let (result, files) = split("suffix-text", "a\nb\n", &["-b", "_%d.txt", "2"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["xx_0.txt", "xx_1.txt"], names(&files));
+    }
+
+    #[test]
+    fn suffix_format_overrides_digits() {
+        let (result, files) = split("suffix-digits", "a\nb\n", &["-n", "4", "-b", "%d", "2"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["xx0", "xx1"], names(&files));
+    }
+
+    #[test]
+    fn suffix_format_hexadecimal() {
+        let args = ["-b", "%02x", "/./", "{*}"];
+        let (result, files) = split("suffix-hex", &numbered_lines(12), &args);
+        assert_matches!(result, Ok(()));
+        assert_eq!(13, files.len());
+        assert_eq!(Some(&"xx0c"), names(&files).last());
+    }
+
+    #[test]
+    fn invalid_suffix_format() {
+        assert_matches!(SuffixFormat::parse("%d%d"), Err(Error::TooManyConversions));
+        assert_matches!(SuffixFormat::parse("%d-%x"), Err(Error::TooManyConversions));
+        assert_matches!(SuffixFormat::parse("abc"), Err(Error::MissingConversion));
+        assert_matches!(SuffixFormat::parse("%%d"), Err(Error::MissingConversion));
+        assert_matches!(SuffixFormat::parse("%03"), Err(Error::MissingConversion));
+        assert_matches!(SuffixFormat::parse("%s"), Err(Error::InvalidConversion('s')));
+
+        let (result, files) = split("suffix-invalid", "a\nb\n", &["-b", "%d%d", "2"]);
+        assert_matches!(result, Err(Error::TooManyConversions));
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn format_suffix() {
+        let format = |format: &str, number| SuffixFormat::parse(format).unwrap().format(number);
+        assert_eq!("007", format("%03d", 7));
+        assert_eq!("  7", format("%3d", 7));
+        assert_eq!("7  |", format("%-3d|", 7));
+        assert_eq!("  007", format("%5.3d", 7));
+        assert_eq!("+7", format("%+d", 7));
+        assert_eq!("0x1f", format("%#x", 31));
+        assert_eq!("0X1F", format("%#X", 31));
+        assert_eq!("017", format("%#o", 15));
+        assert_eq!("%7%", format("%%%d%%", 7));
+        assert_eq!("", format("%.0d", 0));
+    }
+
+    #[test]
+    fn digits() {
+        let (result, files) = split("digits", "a\nb\n", &["-n", "4", "2"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["xx0000", "xx0001"], names(&files));
+
+        let (result, files) = split("digits-zero", "a\nb\n", &["-n", "0", "2"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["xx0", "xx1"], names(&files));
+    }
+
+    #[test]
+    fn digits_widen() {
+        let (result, files) = split("digits-widen", &numbered_lines(101), &["/./", "{*}"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(102, files.len());
+        assert_eq!(["xx98", "xx99", "xx100", "xx101"], names(&files)[98..]);
+    }
+
+    #[test]
+    fn invalid_digits() {
+        let (result, files) = split("digits-invalid", "a\nb\n", &["--digits=-1", "2"]);
+        assert_matches!(result, Err(Error::InvalidNumber(ref n)) if n == "-1");
+        assert!(files.is_empty());
+
+        let (result, _) = split("digits-nan", "a\nb\n", &["-n", "x", "2"]);
+        assert_matches!(result, Err(Error::InvalidNumber(ref n)) if n == "x");
+    }
+
+    #[test]
+    fn keep_files_on_error() {
+        let dir = test_dir("keep", "a\nb\nc\n");
+        let matches = test_args(dir.path(), "xx", &["-k", "/b/", "/zz/"]);
+        let result = run(&matches, &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Err(Error::NotFound(..)));
+        assert!(dir.join("xx00").exists());
+        assert!(dir.join("xx01").exists());
+    }
+
+    #[test]
+    fn remove_files_on_error() {
+        let dir = test_dir("no-keep", "a\nb\nc\n");
+        let matches = test_args(dir.path(), "xx", &["/b/", "/zz/"]);
+        let result = run(&matches, &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Err(Error::NotFound(..)));
+        assert!(!dir.join("xx00").exists());
+        assert!(!dir.join("xx01").exists());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn remove_files_on_write_error() {
+        let dir = test_dir("write-error", "a\nb\nc\nd\n");
+        // Writing to the third file fails as if the disk was full
+        std::os::unix::fs::symlink("/dev/full", dir.join("xx02")).unwrap();
+
+        let matches = test_args(dir.path(), "xx", &["2", "3", "4"]);
+        let mut err = Vec::new();
+        let result = run(&matches, &mut io::sink(), &mut err);
+        assert_matches!(result, Err(Error::WriteFailed(ref f, _)) if f.ends_with("xx02"));
+        assert_eq!(1, exit_status(&result));
+        assert!(String::from_utf8(err).unwrap().is_empty());
+        for name in &["xx00", "xx01", "xx03"] {
+            assert!(!dir.join(name).exists());
+        }
+        assert!(dir.join("xx02").symlink_metadata().is_err());
+    }
+
+    #[test]
+    fn remove_created_reports_failures() {
+        let dir = test_dir("remove-order", "");
+        let created: Vec<_> = ["xx00", "xx01", "xx02"]
+            .iter()
+            .map(|name| dir.join(name).to_str().unwrap().to_string())
+            .collect();
+        fs::write(&created[0], "a").unwrap();
+        fs::write(&created[2], "c").unwrap();
+
+        let mut err = Vec::new();
+        remove_created(&created, &mut err);
+        let err = String::from_utf8(err).unwrap();
+        assert!(err.starts_with(&format!("csplit: cannot remove '{}': ", created[1])));
+        assert_eq!(1, err.lines().count());
+        assert!(!Path::new(&created[0]).exists() && !Path::new(&created[2]).exists());
+    }
+
+    #[test]
+    fn prefix_in_existing_dir() {
+        let dir = test_dir("prefix-dir", "a\nb\n");
+        fs::create_dir(dir.join("out")).unwrap();
+
+        let matches = test_args(dir.path(), "out/part_", &["2"]);
+        let result = run(&matches, &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Ok(()));
+        assert_eq!("a\n", fs::read_to_string(dir.join("out/part_00")).unwrap());
+        assert_eq!("b\n", fs::read_to_string(dir.join("out/part_01")).unwrap());
+    }
+
+    #[test]
+    fn prefix_in_missing_dir() {
+        let dir = test_dir("prefix-missing-dir", "a\nb\n");
+
+        let matches = test_args(dir.path(), "out/sub/part_", &["2"]);
+        let result = run(&matches, &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Err(Error::CreateFailed(..)));
+        assert!(!dir.join("out").exists());
+
+        let matches = test_args(dir.path(), "out/sub/part_", &["--create-dirs", "2"]);
+        let result = run(&matches, &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Ok(()));
+        assert_eq!("a\n", fs::read_to_string(dir.join("out/sub/part_00")).unwrap());
+        assert_eq!("b\n", fs::read_to_string(dir.join("out/sub/part_01")).unwrap());
+    }
+
+    #[test]
+    fn remove_files_of_created_dir_on_error() {
+        let dir = test_dir("prefix-dir-error", "a\nb\nc\n");
+
+        let args = ["--create-dirs", "/b/", "/zz/"];
+        let result = run(&test_args(dir.path(), "out/", &args), &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Err(Error::NotFound(..)));
+        assert!(dir.join("out").is_dir());
+        assert_eq!(0, fs::read_dir(dir.join("out")).unwrap().count());
+    }
+
+    #[test]
+    fn cannot_create_prefix_dir() {
+        let dir = test_dir("prefix-dir-file", "a\nb\n");
+
+        // The directory would be inside of a file
+        let args = ["--create-dirs", "2"];
+        let matches = test_args(dir.path(), "input/part_", &args);
+        let result = run(&matches, &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Err(Error::CreateDirFailed(..)));
+    }
+
+    /// Split `input` with the `args`, and return what was written to the standard output.
+    fn split_output(name: &str, input: &str, args: &[&str]) -> String {
+        let dir = test_dir(name, input);
+        let mut out = Vec::new();
+        run(&test_args(dir.path(), "xx", args), &mut out, &mut io::sink()).unwrap();
+        assert!(dir.join("xx00").exists() && dir.join("xx01").exists());
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn report_sizes() {
+        assert_eq!("2\n7\n", split_output("sizes", "a\n--\nbcd\n", &["/--/"]));
+    }
+
+    #[test]
+    fn silent() {
+        assert_eq!("", split_output("silent", "a\n--\nbcd\n", &["-s", "/--/"]));
+        assert_eq!("", split_output("quiet", "a\n--\nbcd\n", &["--quiet", "/--/"]));
+    }
+
+    /// Split `input` with the `args`, and return what was written to the standard error.
+    fn split_stats(name: &str, input: &str, args: &[&str]) -> String {
+        let dir = test_dir(name, input);
+        let mut err = Vec::new();
+        run(&test_args(dir.path(), "xx", args), &mut io::sink(), &mut err).unwrap();
+        String::from_utf8(err).unwrap()
+    }
+
+    #[test]
+    fn missing_operands() {
+        let matches = cli::create_app().get_matches_from(vec!["csplit"]);
+        let result = run(&matches, &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Err(Error::NoOperand));
+
+        let dir = test_dir("input-only", "a\nb\n");
+        let result = run(&test_args(dir.path(), "xx", &[]), &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Err(Error::MissingOperand(_)));
+        assert!(!dir.join("xx00").exists());
+
+        assert_eq!("missing operand", Error::NoOperand.to_string());
+        assert!(Error::NoOperand.is_usage() && Error::MissingOperand("f".to_string()).is_usage());
+    }
+
+    #[test]
+    fn patterns_before_file() {
+        let dir = test_dir("order", "a\nb\n");
+        let input = dir.join("input").to_str().unwrap().to_string();
+        let prefix = dir.join("xx").to_str().unwrap().to_string();
+
+        let argv = vec!["csplit", "-f", &prefix, "/b/", &input];
+        let matches = cli::create_app().get_matches_from(argv);
+        let result = run(&matches, &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Err(Error::PatternsBeforeFile(ref file)) if file == &input);
+        assert!(!dir.join("xx00").exists());
+
+        // A missing input that is not a pattern is reported as is
+        let missing = dir.join("missing").to_str().unwrap().to_string();
+        let argv = vec!["csplit", "-f", &prefix, &missing, &input];
+        let matches = cli::create_app().get_matches_from(argv);
+        let result = run(&matches, &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Err(Error::OpenFailed(..)));
+    }
+
+    #[test]
+    fn stats() {
+        let input = "a\n--\nbc\n--\ndef\n";
+        assert_eq!(
+            "csplit: 3 files created, 15 bytes written\n",
+            split_stats("stats", input, &["--stats", "/--/", "{1}"])
+        );
+        assert_eq!("", split_stats("no-stats", input, &["/--/", "{1}"]));
+        assert_eq!("", split_stats("stats-silent", input, &["-s", "--stats", "/--/", "{1}"]));
+    }
+
+    #[test]
+    fn stats_without_elided_files() {
+        assert_eq!(
+            "csplit: 1 files created, 5 bytes written\n",
+            split_stats("stats-elide", "--\na\n", &["--stats", "-z", "/--/"])
+        );
+    }
+
+    #[test]
+    fn elide_empty_files() {
+        let input = "a\n--\n--\nb\n";
+        let (result, files) = split("no-elide", input, &["/--/", "{*}"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["a\n", "--\n", "--\nb\n"], contents(&files));
+
+        let (result, files) = split("elide-adjacent", "--\n--\nb\n", &["-z", "/--/", "{*}"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["xx00", "xx01"], names(&files));
+        assert_eq!(vec!["--\n", "--\nb\n"], contents(&files));
+
+        let (result, files) = split("elide-last", "a\n--\n", &["-z", "/--/+1"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["xx00"], names(&files));
+        assert_eq!(vec!["a\n--\n"], contents(&files));
+    }
+
+    #[test]
+    fn elide_empty_sizes() {
+        let dir = test_dir("elide-sizes", "--\na\n");
+        let mut out = Vec::new();
+        run(&test_args(dir.path(), "xx", &["-z", "/--/"]), &mut out, &mut io::sink()).unwrap();
+        assert!(dir.join("xx00").exists() && !dir.join("xx01").exists());
+        assert_eq!("5\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn suppress_matched() {
+        let input = "a\n----\nb\n----\nc\n";
+        let (result, files) = split("suppress", input, &["--suppress-matched", "/----/", "{*}"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["a\n", "b\n", "c\n"], contents(&files));
+        assert!(files.iter().all(|(_, content)| !content.contains("----")));
+
+        let (result, files) = split("suppress-skip", input, &["-x", "%----%"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["b\n----\nc\n"], contents(&files));
+    }
+
+    #[test]
+    fn suppress_matched_with_offset() {
+        let input = "a\n----\nb\n----\nc\n";
+        // The line at the split point is dropped, not the matched one
+        let (result, files) = split("suppress-plus", input, &["-x", "/----/+1"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["a\n----\n", "----\nc\n"], contents(&files));
+
+        let (result, files) = split("suppress-minus", input, &["-x", "/----/-1"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["", "----\nb\n----\nc\n"], contents(&files));
+
+        let (result, files) = split("suppress-line", input, &["-x", "3"]);
+        assert_matches!(result, Ok(()));
+        assert_eq!(vec!["a\n----\n", "----\nc\n"], contents(&files));
+    }
+
+    /// Reader of `count` lines of 8 digits, numbered from 1, generated as they are read.
+    ///
+    /// `on_line` is called with the number of each line when it's generated.
+    struct Generated<F: FnMut(usize)> {
+        count: usize,
+        lineno: usize,
+        line: Vec<u8>,
+        on_line: F,
+    }
+
+    impl<F: FnMut(usize)> Read for Generated<F> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.line.is_empty() {
+                if self.lineno == self.count {
+                    return Ok(0);
+                }
+                self.lineno += 1;
+                (self.on_line)(self.lineno);
+                self.line = format!("{:08}\n", self.lineno).into_bytes();
+            }
+
+            let len = buf.len().min(self.line.len());
+            buf[..len].copy_from_slice(&self.line[..len]);
+            self.line.drain(..len);
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn stream_large_input() {
+        let dir = test_dir("stream", "");
+        let prefix = dir.join("xx").to_str().unwrap().to_string();
+        let (first, second) = (dir.join("xx00"), dir.join("xx01"));
+
+        // When the input is half read, the sections before it are already written
+        let checked = Cell::new(false);
+        let on_line = |lineno| {
+            if lineno == 150_000 {
+                assert_eq!(49_999 * 9, fs::metadata(&first).unwrap().len());
+                assert_eq!(50_000 * 9, fs::metadata(&second).unwrap().len());
+                checked.set(true);
+            }
+        };
+        let generated = Generated { count: 300_000, lineno: 0, line: Vec::new(), on_line };
+        let mut reader = BufReader::new(generated);
+
+        let mut created = Vec::new();
+        let mut out = Vec::new();
+        let mut filer =
+            Filer::new(&prefix, Suffix::Digits(2), &mut created, &mut out, false).unwrap();
+        let patterns = build_patterns(vec!["50000", "{1}", "/^00200000$/-3"], None).unwrap();
+        split_lines(&mut reader, "-", patterns, &mut filer).unwrap();
+        assert!(checked.get());
+
+        let sizes = [49_999 * 9, 50_000 * 9, 99_997 * 9, 100_004 * 9];
+        let expected: String = sizes.iter().map(|size| format!("{}\n", size)).collect();
+        assert_eq!(expected, String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn split_standard_input() {
+        let input = "a\n--\nb\n==\nc\nd\n";
+        let args = ["/--/", "%==%", "/d/"];
+        let (result, on_disk) = split("on-disk", input, &args);
+        assert_matches!(result, Ok(()));
+
+        // The standard input is read the same way, as a `BufRead`
+        let dir = test_dir("stdin", "");
+        let prefix = dir.join("xx").to_str().unwrap().to_string();
+        let (mut created, mut out) = (Vec::new(), io::sink());
+        let mut filer =
+            Filer::new(&prefix, Suffix::Digits(2), &mut created, &mut out, true).unwrap();
+        let patterns = build_patterns(args.to_vec(), None).unwrap();
+        let result = split_lines(&mut input.as_bytes(), "-", patterns, &mut filer);
+        assert_matches!(result, Ok(()));
+        drop(filer);
+
+        let from_stdin: Vec<_> = created.iter().map(|f| fs::read_to_string(f).unwrap()).collect();
+        assert_eq!(contents(&on_disk), from_stdin);
+    }
+
+    #[test]
+    fn not_found_status() {
+        let dir = test_dir("status-not-found", "a\n--\nb\n");
+        let matches = test_args(dir.path(), "xx", &["/--/", "/zz/"]);
+        let result = run(&matches, &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Err(Error::NotFound(ref pat, None)) if pat == "/zz/");
+        assert_eq!(1, exit_status(&result));
+        assert!(!dir.join("xx00").exists() && !dir.join("xx01").exists());
+
+        let dir = test_dir("status-repetition", "a\n--\nb\n");
+        let matches = test_args(dir.path(), "xx", &["/--/", "{1}"]);
+        let result = run(&matches, &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Err(Error::NotFound(ref pat, Some(1))) if pat == "/--/");
+        assert_eq!(1, exit_status(&result));
+        assert!(!dir.join("xx00").exists() && !dir.join("xx01").exists());
+    }
+
+    #[test]
+    fn success_status() {
+        let dir = test_dir("status-ok", "a\n--\nb\n");
+        let result = run(&test_args(dir.path(), "xx", &["/--/"]), &mut io::sink(), &mut io::sink());
+        assert_matches!(result, Ok(()));
+        assert_eq!(0, exit_status(&result));
+        assert!(dir.join("xx00").exists() && dir.join("xx01").exists());
+    }
 }
@@ -0,0 +1,3 @@
+printf 'one\ntwo\n---\nthree\nfour\n---\nfive\n' > input.txt
+grep -v four input.txt | $CSPLIT -f stdin- - /---/ "{*}"
+$CSPLIT -f file- input.txt /---/ "{*}"
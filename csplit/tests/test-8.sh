@@ -0,0 +1,7 @@
+$CSPLIT - /---/ /===/ <<EOF
+one
+two
+---
+three
+four
+EOF
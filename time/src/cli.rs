@@ -1,18 +1,14 @@
 use clap::{
-    crate_authors, crate_description, crate_name, crate_version, App,
-    AppSettings::{ColoredHelp, TrailingVarArg},
+    crate_authors, crate_description, crate_name, crate_version, App, AppSettings::TrailingVarArg,
     Arg,
 };
+use coreutils_core::cli::base_app;
 
 pub(crate) fn create_app<'a, 'b>() -> App<'a, 'b> {
-    App::new(crate_name!())
+    base_app(crate_name!(), crate_description!())
         .version(crate_version!())
         .author(crate_authors!())
-        .about(crate_description!())
-        .help_message("Display help information.")
-        .version_message("Display version information.")
-        .help_short("?")
-        .settings(&[ColoredHelp, TrailingVarArg])
+        .setting(TrailingVarArg)
         .arg(
             Arg::with_name("COMMAND")
                 .help("Command to run and it's arguments.")
@@ -29,4 +25,141 @@ pub(crate) fn create_app<'a, 'b>() -> App<'a, 'b> {
                 .long("posix")
                 .short("p"),
         )
+        .arg(
+            Arg::with_name("output")
+                .help("Write the timing output to FILE instead of the standard error.")
+                .long_help(
+                    "Write the timing output to FILE instead of the standard error.\n\nFILE is \
+                     overwritten if it exists, unless -a is supplied. The output of COMMAND is \
+                     not affected.",
+                )
+                .long("output")
+                .short("o")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("append")
+                .help("Append the timing output to the FILE of -o instead of overwriting it.")
+                .long("append")
+                .short("a")
+                .requires("output"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .help("Use FORMAT to display the time output.")
+                .long_help(
+                    "Use FORMAT to display the time output.\n\n\
+                    FORMAT can contain any of the directives:\n\t\
+                    %e    elapsed wall clock time in seconds\n\t\
+                    %U    user CPU time in seconds\n\t\
+                    %S    system CPU time in seconds\n\t\
+                    %P    percentage of the CPU that COMMAND got\n\t\
+                    %M    maximum resident set size in kilobytes\n\t\
+                    %x    exit status of COMMAND, or 128+N if killed by signal N\n\t\
+                    %C    COMMAND and it's arguments\n\t\
+                    %%    a literal %\n\n\
+                    Without any format option, the format in the TIME environment variable is \
+                    used if it is set.",
+                )
+                .long("format")
+                .short("f")
+                .value_name("FORMAT")
+                .conflicts_with("posix"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .help(
+                    "Display a verbose time output, with the memory usage, page faults and \
+                     context switches of COMMAND.",
+                )
+                .long_help(
+                    "Display a verbose time output, with the memory usage, page faults and \
+                     context switches of COMMAND.\n\nThe memory sizes are in kilobytes of 1024 \
+                     bytes, whatever unit the system reports them in.",
+                )
+                .long("verbose")
+                .short("v")
+                .conflicts_with_all(&["posix", "format"]),
+        )
+        .arg(
+            Arg::with_name("json")
+                .help(
+                    "Display the time output as a JSON object with the keys: real, user, sys, \
+                     max_rss_kb, exit_code and command.",
+                )
+                .long("json")
+                .conflicts_with_all(&["posix", "format", "verbose"]),
+        )
+        .arg(
+            Arg::with_name("csv")
+                .help(
+                    "Display the time output as CSV, with a header row of the columns command, \
+                     real, user, sys, max_rss and exit, and a row for each run.",
+                )
+                .long_help(
+                    "Display the time output as CSV, with a header row of the columns command, \
+                     real, user, sys, max_rss and exit, and a row for each run.\n\nThe times \
+                     are in seconds and max_rss in kilobytes. The summary of --repeat is not \
+                     displayed, so that the output is a single table.",
+                )
+                .long("csv")
+                .conflicts_with_all(&["posix", "format", "verbose", "json"]),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .help(
+                    "Do not display the time output if COMMAND succeeds, nor the note about \
+                     COMMAND being too fast to measure.",
+                )
+                .long("quiet")
+                .short("q"),
+        )
+        .arg(
+            Arg::with_name("min_seconds")
+                .help("Display the time output only if COMMAND took at least N seconds.")
+                .long("min-seconds")
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("repeat")
+                .help(
+                    "Run COMMAND N times and display the min, mean, max and standard deviation \
+                     of the real and user times after the time output of each run.",
+                )
+                .long("repeat")
+                .short("n")
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("stdout")
+                .help("Send the standard output of COMMAND to TARGET.")
+                .long_help(
+                    "Send the standard output of COMMAND to TARGET.\n\nTARGET can be inherit, the \
+                     default, to use the standard output of time, /dev/null or NUL to discard \
+                     it, or the path of a file to write it to. The file is overwritten if it \
+                     exists.",
+                )
+                .long("stdout")
+                .value_name("TARGET"),
+        )
+        .arg(
+            Arg::with_name("stderr")
+                .help("Send the standard error of COMMAND to TARGET, as with --stdout.")
+                .long("stderr")
+                .value_name("TARGET"),
+        )
+        .arg(
+            Arg::with_name("child_tree")
+                .help(
+                    "Include the CPU time of all the processes started by COMMAND, even the ones \
+                     it did not wait for. Only on Linux.",
+                )
+                .long_help(
+                    "Include the CPU time of all the processes started by COMMAND, even the ones \
+                     it did not wait for. Only on Linux.\n\nCOMMAND is run in its own process \
+                     group, and time waits for every process of the group to end before \
+                     reporting.",
+                )
+                .long("child-tree"),
+        )
 }
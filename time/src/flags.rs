@@ -1,8 +1,15 @@
 //! Command line options that are supported by `time`
 
+use std::env;
+
 use clap::ArgMatches;
+use coreutils_core::exit::{self, die};
 
-use crate::{cli::create_app, output::OutputFormatter};
+use crate::{
+    cli::create_app,
+    output::{OutputFormatter, RunStats},
+    subprocess::ChildOutput,
+};
 
 // Condense CLI args as a struct
 #[derive(Debug)]
@@ -11,6 +18,22 @@ pub struct TimeOpts {
     pub printer: OutputFormatter,
     /// Command as seen on the CLI
     pub command: Vec<String>,
+    /// File to write the stats to instead of stderr
+    pub output: Option<String>,
+    /// Append to the output file instead of truncating it
+    pub append: bool,
+    /// Do not report on a command that succeeds
+    pub quiet: bool,
+    /// Report only on a command that took at least that many seconds
+    pub min_seconds: Option<f64>,
+    /// Number of times the command is run
+    pub repeat: usize,
+    /// Where the standard output of the command goes
+    pub stdout: ChildOutput,
+    /// Where the standard error of the command goes
+    pub stderr: ChildOutput,
+    /// Account for all the descendants of the command, not only the ones it waited for
+    pub child_tree: bool,
 }
 
 impl TimeOpts {
@@ -20,23 +43,93 @@ impl TimeOpts {
 
     pub fn new(args: ArgMatches) -> Self {
         TimeOpts {
-            printer: if args.is_present("posix") {
-                OutputFormatter::Posix
-            } else {
-                OutputFormatter::Default
-            },
+            printer: output_formatter(&args, env::var("TIME").ok()),
             command: args
                 .values_of("COMMAND")
                 .expect("`COMMAND` value cannot be `None`, it is required.")
                 .map(str::to_owned)
                 .collect(),
+            output: args.value_of("output").map(str::to_owned),
+            append: args.is_present("append"),
+            quiet: args.is_present("quiet"),
+            min_seconds: args.value_of("min_seconds").map(parse_seconds),
+            repeat: args.value_of("repeat").map_or(1, parse_repeat),
+            stdout: args.value_of("stdout").map_or(ChildOutput::Inherit, ChildOutput::from_arg),
+            stderr: args.value_of("stderr").map_or(ChildOutput::Inherit, ChildOutput::from_arg),
+            child_tree: parse_child_tree(args.is_present("child_tree")),
         }
     }
+
+    /// Whether the `stats` of the command should be reported, according to `-q` and
+    /// `--min-seconds`
+    pub fn should_report(&self, stats: &RunStats) -> bool {
+        let quieted = self.quiet && stats.exit_status.success();
+        let too_fast = matches!(self.min_seconds, Some(min) if stats.wall_secs() < min);
+        !quieted && !too_fast
+    }
+
+    /// Whether the summary of the repeated `runs` should be reported, that is not the case with
+    /// `-q` if every run succeeded, nor for formats that have no room for it
+    pub fn should_summarize(&self, runs: &[RunStats]) -> bool {
+        let quieted = self.quiet && runs.iter().all(|stats| stats.exit_status.success());
+        self.repeat > 1 && self.printer.with_summary() && !quieted
+    }
+}
+
+/// Formatter chosen by the flags in `args`, or by the format in `time_var`, the value of the
+/// `TIME` environment variable, if none is given
+fn output_formatter(args: &ArgMatches, time_var: Option<String>) -> OutputFormatter {
+    if args.is_present("posix") {
+        OutputFormatter::Posix
+    } else if args.is_present("verbose") {
+        OutputFormatter::Verbose
+    } else if args.is_present("json") {
+        OutputFormatter::Json
+    } else if args.is_present("csv") {
+        OutputFormatter::Csv
+    } else if let Some(format) = args.value_of("format") {
+        OutputFormatter::Custom(format.to_owned())
+    } else if let Some(format) = time_var.filter(|format| !format.is_empty()) {
+        OutputFormatter::Custom(format)
+    } else {
+        OutputFormatter::Default
+    }
+}
+
+fn parse_seconds(s: &str) -> f64 {
+    match s.parse::<f64>() {
+        Ok(secs) if secs >= 0.0 => secs,
+        _ => die(exit::CANCELED, format!("invalid number of seconds: '{}'", s)),
+    }
+}
+
+fn parse_repeat(s: &str) -> usize {
+    match s.parse::<usize>() {
+        Ok(count) if count > 0 => count,
+        _ => die(exit::CANCELED, format!("invalid number of runs: '{}'", s)),
+    }
+}
+
+/// Whether `--child-tree` is `present`, exiting if it's not supported on this platform
+fn parse_child_tree(present: bool) -> bool {
+    if present && !cfg!(target_os = "linux") {
+        die(exit::CANCELED, "--child-tree is only supported on Linux");
+    }
+    present
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{create_app, OutputFormatter, TimeOpts};
+    use std::{os::unix::process::ExitStatusExt, process::ExitStatus, time::Duration};
+
+    use clap::ErrorKind;
+    use coreutils_core::{
+        cli::HELP_FOOTER,
+        os::resource::{get_rusage, ResourceConsumer},
+    };
+
+    use crate::output::CSV_HEADER;
+    use super::{create_app, output_formatter, ChildOutput, OutputFormatter, RunStats, TimeOpts};
 
     #[test]
     fn parsing_valid_command_with_args() {
@@ -55,4 +148,202 @@ mod tests {
 
         assert_eq!(OutputFormatter::Posix, opts.printer);
     }
+
+    #[test]
+    fn parse_custom_format() {
+        let args = vec!["test-time", "-f", "%e real", "cmd-to-run"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+
+        assert_eq!(OutputFormatter::Custom("%e real".to_owned()), opts.printer);
+
+        let args = vec!["test-time", "-p", "-f", "%e real", "cmd-to-run"];
+        assert!(create_app().get_matches_from_safe(args).is_err());
+    }
+
+    #[test]
+    fn parse_output_file() {
+        let args = vec!["test-time", "-o", "log.txt", "cmd-to-run", "arg1"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+
+        assert_eq!(Some("log.txt".to_owned()), opts.output);
+        assert_eq!(vec!["cmd-to-run", "arg1"], opts.command);
+
+        let args = vec!["test-time", "cmd-to-run", "-o", "log.txt"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+
+        assert_eq!(None, opts.output);
+        assert_eq!(vec!["cmd-to-run", "-o", "log.txt"], opts.command);
+    }
+
+    #[test]
+    fn parse_append_requires_output() {
+        let args = vec!["test-time", "-a", "-o", "log.txt", "cmd-to-run"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+
+        assert!(opts.append);
+        assert_eq!(Some("log.txt".to_owned()), opts.output);
+
+        let args = vec!["test-time", "-a", "cmd-to-run"];
+        assert!(create_app().get_matches_from_safe(args).is_err());
+    }
+
+    #[test]
+    fn parse_verbose() {
+        let args = vec!["test-time", "-v", "cmd-to-run"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+
+        assert_eq!(OutputFormatter::Verbose, opts.printer);
+
+        let args = vec!["test-time", "-v", "-p", "cmd-to-run"];
+        assert!(create_app().get_matches_from_safe(args).is_err());
+    }
+
+    #[test]
+    fn parse_json() {
+        let args = vec!["test-time", "--json", "cmd-to-run"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+
+        assert_eq!(OutputFormatter::Json, opts.printer);
+
+        for flag in &["-p", "-v", "-f=%e"] {
+            let args = vec!["test-time", "--json", flag, "cmd-to-run"];
+            assert!(create_app().get_matches_from_safe(args).is_err());
+        }
+    }
+
+    #[test]
+    fn parse_csv() {
+        let args = vec!["test-time", "--csv", "cmd-to-run"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+
+        assert_eq!(OutputFormatter::Csv, opts.printer);
+        assert_eq!(Some(CSV_HEADER), opts.printer.header());
+        assert!(!opts.printer.with_summary());
+
+        for flag in &["-p", "-v", "-f=%e", "--json"] {
+            let args = vec!["test-time", "--csv", flag, "cmd-to-run"];
+            assert!(create_app().get_matches_from_safe(args).is_err());
+        }
+    }
+
+    fn run_stats(command: &[String], exit_code: i32, wall_millis: u64) -> RunStats<'_> {
+        RunStats {
+            command,
+            exit_status: ExitStatus::from_raw(exit_code << 8),
+            wall_time: Duration::from_millis(wall_millis),
+            rusage: get_rusage(ResourceConsumer::Caller),
+        }
+    }
+
+    #[test]
+    fn quiet_reports_only_failures() {
+        let args = vec!["test-time", "-q", "cmd-to-run"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+        let command = opts.command.clone();
+
+        assert!(!opts.should_report(&run_stats(&command, 0, 100)));
+        assert!(opts.should_report(&run_stats(&command, 1, 100)));
+    }
+
+    #[test]
+    fn quiet_summarizes_only_failures() {
+        let args = vec!["test-time", "-q", "-n", "3", "cmd-to-run"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+        let command = opts.command.clone();
+        let ok = || run_stats(&command, 0, 100);
+
+        assert!(!opts.should_summarize(&[ok(), ok(), ok()]));
+        assert!(opts.should_summarize(&[ok(), run_stats(&command, 1, 100), ok()]));
+
+        let args = vec!["test-time", "-n", "3", "cmd-to-run"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+        assert!(opts.should_summarize(&[ok(), ok(), ok()]));
+
+        let args = vec!["test-time", "cmd-to-run"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+        assert!(!opts.should_summarize(&[ok()]));
+    }
+
+    #[test]
+    fn min_seconds_reports_only_slow_runs() {
+        let args = vec!["test-time", "--min-seconds", "1.5", "cmd-to-run"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+        let command = opts.command.clone();
+
+        assert_eq!(Some(1.5), opts.min_seconds);
+        assert!(!opts.should_report(&run_stats(&command, 0, 1000)));
+        assert!(opts.should_report(&run_stats(&command, 0, 2000)));
+
+        let args = vec!["test-time", "cmd-to-run"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+        assert!(opts.should_report(&run_stats(&command, 0, 0)));
+    }
+
+    #[test]
+    fn help_ends_with_footer() {
+        let err = create_app().get_matches_from_safe(vec!["test-time", "--help"]).unwrap_err();
+
+        assert_eq!(ErrorKind::HelpDisplayed, err.kind);
+        assert!(err.message.trim_end().ends_with(HELP_FOOTER));
+    }
+
+    #[test]
+    fn parse_repeat_count() {
+        let args = vec!["test-time", "cmd-to-run"];
+        assert_eq!(1, TimeOpts::new(create_app().get_matches_from(args)).repeat);
+
+        let args = vec!["test-time", "-n", "5", "cmd-to-run", "-n", "2"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+        assert_eq!(5, opts.repeat);
+        assert_eq!(vec!["cmd-to-run", "-n", "2"], opts.command);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn parse_child_tree_flag() {
+        let args = vec!["test-time", "cmd-to-run"];
+        assert!(!TimeOpts::new(create_app().get_matches_from(args)).child_tree);
+
+        let args = vec!["test-time", "--child-tree", "cmd-to-run"];
+        assert!(TimeOpts::new(create_app().get_matches_from(args)).child_tree);
+    }
+
+    #[test]
+    fn parse_child_outputs() {
+        let args = vec!["test-time", "cmd-to-run"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+        assert_eq!(ChildOutput::Inherit, opts.stdout);
+        assert_eq!(ChildOutput::Inherit, opts.stderr);
+
+        let args = vec!["test-time", "--stdout=/dev/null", "--stderr", "err.log", "cmd-to-run"];
+        let opts = TimeOpts::new(create_app().get_matches_from(args));
+        assert_eq!(ChildOutput::Null, opts.stdout);
+        assert_eq!(ChildOutput::File("err.log".to_owned()), opts.stderr);
+    }
+
+    #[test]
+    fn format_from_time_variable() {
+        let command = vec!["cmd-to-run".to_owned()];
+        let args = create_app().get_matches_from(vec!["test-time", "cmd-to-run"]);
+        let printer = output_formatter(&args, Some("%e".to_owned()));
+
+        assert_eq!(OutputFormatter::Custom("%e".to_owned()), printer);
+        assert_eq!("1.25", printer.format_stats(&run_stats(&command, 0, 1250)));
+
+        // Unset or empty, the default format is used
+        assert_eq!(OutputFormatter::Default, output_formatter(&args, None));
+        assert_eq!(OutputFormatter::Default, output_formatter(&args, Some(String::new())));
+    }
+
+    #[test]
+    fn format_flags_override_time_variable() {
+        let time_var = Some("%e".to_owned());
+
+        let args = create_app().get_matches_from(vec!["test-time", "-f", "%U", "cmd-to-run"]);
+        let printer = output_formatter(&args, time_var.clone());
+        assert_eq!(OutputFormatter::Custom("%U".to_owned()), printer);
+
+        let args = create_app().get_matches_from(vec!["test-time", "-p", "cmd-to-run"]);
+        assert_eq!(OutputFormatter::Posix, output_formatter(&args, time_var));
+    }
 }
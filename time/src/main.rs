@@ -3,17 +3,45 @@ mod flags;
 mod output;
 mod subprocess;
 
-use coreutils_core::os::resource::{get_rusage, ResourceConsumer};
+use std::io::Write;
+
+use coreutils_core::exit::{self, die};
 
 fn main() {
     let opts = flags::TimeOpts::from_matches();
-    let (exit_status, duration) = match subprocess::timed_run(&opts.command) {
-        Ok(rv) => rv,
-        Err(err) => subprocess::exit_with_msg(err),
-    };
+    let mut stats_writer = output::open_stats_writer(opts.output.as_deref(), opts.append);
 
-    let usage = get_rusage(ResourceConsumer::Children);
+    let stdio = subprocess::ChildStdio::open(&opts.stdout, &opts.stderr)
+        .unwrap_or_else(|err| die(exit::CANCELED, err));
+
+    let runs = match subprocess::timed_runs(&opts.command, opts.repeat, &stdio, opts.child_tree) {
+        Ok(runs) => runs,
+        Err(err) => subprocess::exit_with_msg(&opts.command[0], err),
+    };
 
-    eprintln!("{}", opts.printer.format_stats(&usage, &duration));
-    std::process::exit(exit_status.code().unwrap_or(1));
+    let mut reported = runs.iter().filter(|stats| opts.should_report(stats)).peekable();
+    if let (Some(header), Some(_)) = (opts.printer.header(), reported.peek()) {
+        if let Err(err) = writeln!(stats_writer, "{}", header) {
+            eprintln!("time: cannot write the stats: {}", err);
+        }
+    }
+    for stats in reported {
+        if let Err(err) = writeln!(stats_writer, "{}", opts.printer.format_stats(stats)) {
+            eprintln!("time: cannot write the stats: {}", err);
+        }
+    }
+    // Calibrated once the command is done, so it doesn't compete with it for the CPU, and only
+    // if a run is short enough for the resolution to matter
+    if !opts.quiet && runs.iter().any(subprocess::Resolution::may_be_too_fast) {
+        let resolution = subprocess::Resolution::calibrate();
+        if runs.iter().any(|stats| resolution.is_too_fast(stats)) {
+            eprintln!("time: command too fast to measure accurately");
+        }
+    }
+    if opts.should_summarize(&runs) {
+        if let Err(err) = writeln!(stats_writer, "{}", output::summary_formatter(&runs)) {
+            eprintln!("time: cannot write the stats: {}", err);
+        }
+    }
+    std::process::exit(output::repeated_exit_code(&runs));
 }
@@ -1,11 +1,40 @@
 //! Output interface for `time`
 
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    os::unix::process::ExitStatusExt,
+    process::ExitStatus,
+    time::Duration,
+};
+
 use coreutils_core::os::{resource::RUsage, TimeVal};
 
 #[derive(Debug, PartialEq)]
 pub enum OutputFormatter {
     Default,
     Posix,
+    /// Multi-line report of all the resources used, as GNU `time -v`
+    Verbose,
+    /// Single JSON object, for machine consumption
+    Json,
+    /// Row of a CSV table, for spreadsheets, after the [`CSV_HEADER`]
+    Csv,
+    /// User defined format, with `%` directives
+    Custom(String),
+}
+
+/// Everything measured from a run of the command
+#[derive(Debug)]
+pub struct RunStats<'a> {
+    /// Command and it's arguments
+    pub command: &'a [String],
+    /// Exit status of the command
+    pub exit_status: ExitStatus,
+    /// Elapsed wall clock time
+    pub wall_time: Duration,
+    /// Resources used by the command
+    pub rusage: RUsage,
 }
 
 /// Express `coreutils_core::os::TimeVal` into `f64` seconds
@@ -13,20 +42,607 @@ fn as_secs_f64(tv: TimeVal) -> f64 {
     tv.tv_sec as f64 + (tv.tv_usec as f64) / 1_000_000.0
 }
 
+impl RunStats<'_> {
+    /// Elapsed wall clock time in seconds
+    pub fn wall_secs(&self) -> f64 {
+        self.wall_time.as_secs_f64()
+    }
+
+    /// User CPU time in seconds
+    pub fn user_secs(&self) -> f64 {
+        as_secs_f64(self.rusage.timing.user_time)
+    }
+
+    /// System CPU time in seconds
+    pub fn sys_secs(&self) -> f64 {
+        as_secs_f64(self.rusage.timing.sys_time)
+    }
+
+    /// Percentage of the CPU that the command got, or `None` if no time elapsed
+    pub fn cpu_percent(&self) -> Option<f64> {
+        let wall_time = self.wall_secs();
+        if wall_time > 0.0 {
+            Some((self.user_secs() + self.sys_secs()) / wall_time * 100.0)
+        } else {
+            None
+        }
+    }
+
+    /// Percentage of the CPU that the command got, rounded, or `?%` if no time elapsed
+    pub fn cpu_percent_str(&self) -> String {
+        match self.cpu_percent() {
+            Some(percent) => format!("{:.0}%", percent),
+            None => "?%".to_owned(),
+        }
+    }
+
+    /// Maximum resident set size in bytes
+    pub fn max_rss_bytes(&self) -> u64 {
+        // macOS reports it in bytes, everyone else in kilobytes
+        if cfg!(target_os = "macos") {
+            self.rusage.mem.max_rss
+        } else {
+            self.rusage.mem.max_rss.saturating_mul(1024)
+        }
+    }
+
+    /// Maximum resident set size in kilobytes, of 1024 bytes
+    pub fn max_rss_kb(&self) -> u64 {
+        self.max_rss_bytes() / 1024
+    }
+
+    /// Exit code of the command, or 128 + N if it was killed by signal N
+    pub fn exit_code(&self) -> i32 {
+        match self.exit_status.signal() {
+            Some(signal) => 128 + signal,
+            None => self.exit_status.code().unwrap_or(1),
+        }
+    }
+
+    /// How the command ended, as `exit N` or `signal N`, or `None` if it succeeded
+    pub fn exit_note(&self) -> Option<String> {
+        match (self.exit_status.signal(), self.exit_status.code()) {
+            (Some(signal), _) => Some(format!("signal {}", signal)),
+            (None, Some(0)) => None,
+            (None, _) => Some(format!("exit {}", self.exit_code())),
+        }
+    }
+}
+
 impl OutputFormatter {
-    pub fn format_stats(self, rusage: &RUsage, duration: &std::time::Duration) -> String {
-        let wall_time = duration.as_secs_f64();
-        let user_time = as_secs_f64(rusage.timing.user_time);
-        let sys_time = as_secs_f64(rusage.timing.sys_time);
+    /// Line to write before the reports of the runs, if any
+    pub fn header(&self) -> Option<&'static str> {
         match self {
-            OutputFormatter::Default => default_formatter(rusage, wall_time, user_time, sys_time),
+            OutputFormatter::Csv => Some(CSV_HEADER),
+            _ => None,
+        }
+    }
+
+    /// Whether the summary of repeated runs can be written after the reports, which is not the
+    /// case of the rows of a CSV table
+    pub fn with_summary(&self) -> bool {
+        *self != OutputFormatter::Csv
+    }
+
+    pub fn format_stats(&self, stats: &RunStats) -> String {
+        let wall_time = stats.wall_secs();
+        let user_time = stats.user_secs();
+        let sys_time = stats.sys_secs();
+        match self {
+            OutputFormatter::Default => default_formatter(stats),
             OutputFormatter::Posix => {
                 format!("real {:.2}\nuser {:.2}\nsys  {:.2}", wall_time, user_time, sys_time)
             },
+            OutputFormatter::Verbose => verbose_formatter(stats),
+            OutputFormatter::Json => json_formatter(stats),
+            OutputFormatter::Csv => csv_formatter(stats),
+            OutputFormatter::Custom(format) => custom_formatter(format, stats),
+        }
+    }
+}
+
+/// Format the `stats` as shells do, like `0.42s user 0.01s system 98% cpu 0.438 total`, with
+/// the units of the times, followed by a line with how the command ended if it failed
+pub fn default_formatter(stats: &RunStats) -> String {
+    // Long runs are easier to read in minutes and hours, as `%E` shows them
+    let wall = stats.wall_secs();
+    let total = if wall >= 60.0 { format_elapsed(wall) } else { format!("{:.3}", wall) };
+    let report = format!(
+        "{:.2}s user {:.2}s system {} cpu {} total",
+        stats.user_secs(),
+        stats.sys_secs(),
+        stats.cpu_percent_str(),
+        total
+    );
+
+    match stats.exit_note() {
+        Some(note) => format!("{}\n{}", report, note),
+        None => report,
+    }
+}
+
+/// Format the elapsed `secs` as `h:mm:ss`, or as `m:ss.ss` under an hour
+fn format_elapsed(secs: f64) -> String {
+    // Rounded to hundredths first, so that 59.999 seconds is `1:00.00` and not `0:60.00`
+    let centis = (secs * 100.0).round() as u64;
+    let (hours, mins, secs) = (centis / 360_000, centis / 6000 % 60, centis / 100 % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, mins, secs)
+    } else {
+        format!("{}:{:02}.{:02}", mins, secs, centis % 100)
+    }
+}
+
+/// Format the `stats` as a multi-line report, labeled as GNU `time -v` does
+pub fn verbose_formatter(stats: &RunStats) -> String {
+    let mem = &stats.rusage.mem;
+    let io = &stats.rusage.io;
+    let percent = stats.cpu_percent_str();
+    let elapsed = format_elapsed(stats.wall_secs());
+
+    let lines = [
+        format!("Command being timed: \"{}\"", stats.command.join(" ")),
+        format!("User time (seconds): {:.2}", stats.user_secs()),
+        format!("System time (seconds): {:.2}", stats.sys_secs()),
+        format!("Percent of CPU this job got: {}", percent),
+        format!("Elapsed (wall clock) time (h:mm:ss or m:ss): {}", elapsed),
+        format!("Average shared text size (kbytes): {}", mem.shared_mem_size),
+        format!("Average unshared data size (kbytes): {}", mem.unshared_data_size),
+        format!("Average stack size (kbytes): {}", mem.unshared_stack_size),
+        format!("Maximum resident set size (kbytes): {}", stats.max_rss_kb()),
+        format!("Major (requiring I/O) page faults: {}", mem.num_major_page_flt),
+        format!("Minor (reclaiming a frame) page faults: {}", mem.num_minor_page_flt),
+        format!("Voluntary context switches: {}", mem.num_vol_ctx_switch),
+        format!("Involuntary context switches: {}", mem.num_invol_ctx_switch),
+        format!("Swaps: {}", mem.num_swaps),
+        format!("File system inputs: {}", io.num_block_in),
+        format!("File system outputs: {}", io.num_block_out),
+        format!("Socket messages sent: {}", io.num_sock_send),
+        format!("Socket messages received: {}", io.num_sock_recv),
+        format!("Signals delivered: {}", io.num_signals),
+        format!("Exit status: {}", stats.exit_code()),
+    ];
+
+    lines.iter().map(|line| format!("\t{}", line)).collect::<Vec<_>>().join("\n")
+}
+
+/// Format the `stats` as a single JSON object
+pub fn json_formatter(stats: &RunStats) -> String {
+    format!(
+        "{{\"real\":{:.2},\"user\":{:.2},\"sys\":{:.2},\"max_rss_kb\":{},\"exit_code\":{},\
+         \"command\":\"{}\"}}",
+        stats.wall_secs(),
+        stats.user_secs(),
+        stats.sys_secs(),
+        stats.max_rss_kb(),
+        stats.exit_code(),
+        json_escape(&stats.command.join(" "))
+    )
+}
+
+/// Escape `s` to be used inside a JSON string
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Header of the CSV table of [`csv_formatter`] rows
+pub const CSV_HEADER: &str = "command,real,user,sys,max_rss,exit";
+
+/// Format the `stats` as a row of a CSV table, with the columns of [`CSV_HEADER`]. The maximum
+/// resident set size is in kilobytes
+pub fn csv_formatter(stats: &RunStats) -> String {
+    format!(
+        "{},{:.2},{:.2},{:.2},{},{}",
+        csv_escape(&stats.command.join(" ")),
+        stats.wall_secs(),
+        stats.user_secs(),
+        stats.sys_secs(),
+        stats.max_rss_kb(),
+        stats.exit_code()
+    )
+}
+
+/// Escape `s` to be a field of a CSV row, quoting it if it has a comma, a quote or a line break
+fn csv_escape(s: &str) -> String {
+    if s.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+/// Format the `stats` acording to the user defined `format`.
+///
+/// Supported directives are:
+/// - `%e`: elapsed wall clock time in seconds
+/// - `%U`: user CPU time in seconds
+/// - `%S`: system CPU time in seconds
+/// - `%P`: percentage of the CPU that the command got
+/// - `%M`: maximum resident set size in kilobytes
+/// - `%x`: exit status of the command, or 128 + N if it was killed by signal N
+/// - `%C`: command and it's arguments
+/// - `%%`: a literal `%`
+///
+/// Unknown directives are written verbatim, with a warning printed to stderr.
+pub fn custom_formatter(format: &str, stats: &RunStats) -> String {
+    let mut out = String::with_capacity(format.len());
+    let mut chars = format.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('e') => out.push_str(&format!("{:.2}", stats.wall_secs())),
+            Some('U') => out.push_str(&format!("{:.2}", stats.user_secs())),
+            Some('S') => out.push_str(&format!("{:.2}", stats.sys_secs())),
+            Some('P') => out.push_str(&stats.cpu_percent_str()),
+            Some('M') => out.push_str(&stats.max_rss_kb().to_string()),
+            Some('x') => out.push_str(&stats.exit_code().to_string()),
+            Some('C') => out.push_str(&stats.command.join(" ")),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                eprintln!("time: warning: unknown format directive '%{}'", other);
+                out.push('%');
+                out.push(other);
+            },
+            None => {
+                eprintln!("time: warning: trailing '%' in format");
+                out.push('%');
+            },
         }
     }
+
+    out
+}
+
+/// Minimum, mean, maximum and standard deviation of a series of times
+#[derive(Debug, PartialEq)]
+struct Spread {
+    min: f64,
+    mean: f64,
+    max: f64,
+    stddev: f64,
+}
+
+impl Spread {
+    /// Spread of `values`, that must not be empty
+    fn of(values: &[f64]) -> Self {
+        let count = values.len() as f64;
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mean = values.iter().sum::<f64>() / count;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count;
+
+        Spread { min, mean, max, stddev: variance.sqrt() }
+    }
+}
+
+/// Format the spread of the real and user times of the `runs` of a command, that must not be
+/// empty
+pub fn summary_formatter(runs: &[RunStats]) -> String {
+    let wall: Vec<_> = runs.iter().map(RunStats::wall_secs).collect();
+    let user: Vec<_> = runs.iter().map(RunStats::user_secs).collect();
+
+    let mut lines = vec![format!("Runs: {}", runs.len())];
+    for (name, spread) in &[("real", Spread::of(&wall)), ("user", Spread::of(&user))] {
+        lines.push(format!(
+            "{} min {:.2} mean {:.2} max {:.2} stddev {:.2}",
+            name, spread.min, spread.mean, spread.max, spread.stddev
+        ));
+    }
+
+    lines.join("\n")
+}
+
+/// Exit code of a command run several times: the one of the last run, or the one of the last
+/// failed run if the last run succeeded
+pub fn repeated_exit_code(runs: &[RunStats]) -> i32 {
+    runs.iter().rev().map(RunStats::exit_code).find(|&code| code != 0).unwrap_or(0)
+}
+
+/// Open the stream the stats are written to: the file at `path`, or stderr if `path` is
+/// `None`.
+///
+/// The file is truncated, unless `append` is set. If it cannot be opened, an error message is
+/// printed and stderr is used instead, so the command still runs and its stats are not lost.
+pub fn open_stats_writer(path: Option<&str>, append: bool) -> Box<dyn Write> {
+    match path {
+        Some(path) => {
+            let file = if append {
+                OpenOptions::new().append(true).create(true).open(path)
+            } else {
+                File::create(path)
+            };
+
+            match file {
+                Ok(file) => Box::new(file),
+                Err(err) => {
+                    eprintln!("time: cannot open '{}': {}", path, err);
+                    Box::new(io::stderr())
+                },
+            }
+        },
+        None => Box::new(io::stderr()),
+    }
 }
 
-pub fn default_formatter(_: &RUsage, wall_time: f64, user_time: f64, sys_time: f64) -> String {
-    format!("{:.2} real {:.2} user {:.2} sys", wall_time, user_time, sys_time)
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn append_stats_to_output_file() {
+        let path = std::env::temp_dir().join(format!("time-append-{}.log", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        for _ in 0..2 {
+            let mut writer = open_stats_writer(Some(path), true);
+            writeln!(writer, "0.00 real 0.00 user 0.00 sys").unwrap();
+        }
+        let content = fs::read_to_string(path).unwrap();
+        assert_eq!(2, content.lines().filter(|l| l.ends_with(" sys")).count());
+
+        // Without append the file is overwritten
+        let mut writer = open_stats_writer(Some(path), false);
+        writeln!(writer, "0.00 real 0.00 user 0.00 sys").unwrap();
+        drop(writer);
+        let content = fs::read_to_string(path).unwrap();
+        assert_eq!(1, content.lines().count());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    fn stats_fixture(command: &[String]) -> RunStats<'_> {
+        let mut rusage = coreutils_core::os::resource::get_rusage(
+            coreutils_core::os::resource::ResourceConsumer::Caller,
+        );
+        rusage.timing.user_time.tv_sec = 1;
+        rusage.timing.user_time.tv_usec = 500_000;
+        rusage.timing.sys_time.tv_sec = 0;
+        rusage.timing.sys_time.tv_usec = 250_000;
+        rusage.mem.max_rss = 2048;
+
+        RunStats {
+            command,
+            // Exit code 3, as returned by `waitpid`
+            exit_status: ExitStatus::from_raw(3 << 8),
+            wall_time: Duration::from_millis(3500),
+            rusage,
+        }
+    }
+
+    #[test]
+    fn default_report() {
+        let command = vec!["sleep".to_owned(), "1".to_owned()];
+        let mut stats = stats_fixture(&command);
+        stats.exit_status = ExitStatus::from_raw(0);
+
+        assert_eq!("1.50s user 0.25s system 50% cpu 3.500 total", default_formatter(&stats));
+    }
+
+    #[test]
+    fn default_report_of_failed_command() {
+        let command = vec!["false".to_owned()];
+        let mut stats = stats_fixture(&command);
+
+        assert_eq!(
+            "1.50s user 0.25s system 50% cpu 3.500 total\nexit 3",
+            default_formatter(&stats)
+        );
+
+        // Killed by `SIGKILL`, as returned by `waitpid`
+        stats.exit_status = ExitStatus::from_raw(9);
+        assert_eq!(
+            "1.50s user 0.25s system 50% cpu 3.500 total\nsignal 9",
+            default_formatter(&stats)
+        );
+        assert_eq!("137", custom_formatter("%x", &stats));
+    }
+
+    #[test]
+    fn custom_format_directives() {
+        let command = vec!["sleep".to_owned(), "1".to_owned()];
+        let stats = stats_fixture(&command);
+
+        assert_eq!(
+            "3.50 real 1.50 user 0.25 sys",
+            custom_formatter("%e real %U user %S sys", &stats)
+        );
+        assert_eq!(
+            format!("50% {}KB 3 %", stats.max_rss_kb()),
+            custom_formatter("%P %MKB %x %%", &stats)
+        );
+        assert_eq!("cmd: sleep 1", custom_formatter("cmd: %C", &stats));
+        assert_eq!("%q %", custom_formatter("%q %", &stats));
+    }
+
+    #[test]
+    fn custom_format_percent_without_elapsed_time() {
+        let command = vec!["true".to_owned()];
+        let mut stats = stats_fixture(&command);
+        stats.wall_time = Duration::from_secs(0);
+
+        assert_eq!("?%", custom_formatter("%P", &stats));
+        assert_eq!(
+            "1.50s user 0.25s system ?% cpu 0.000 total\nexit 3",
+            default_formatter(&stats)
+        );
+    }
+
+    #[test]
+    fn exit_code_of_killed_command() {
+        let command = vec!["sleep".to_owned(), "1".to_owned()];
+        let mut stats = stats_fixture(&command);
+        assert_eq!(3, stats.exit_code());
+
+        // Killed by `SIGKILL`, as returned by `waitpid`
+        stats.exit_status = ExitStatus::from_raw(9);
+        assert_eq!(137, stats.exit_code());
+    }
+
+    #[test]
+    fn verbose_report() {
+        let command = vec!["sleep".to_owned(), "1".to_owned()];
+        let stats = stats_fixture(&command);
+        let report = verbose_formatter(&stats);
+
+        assert!(report.starts_with("\tCommand being timed: \"sleep 1\"\n"));
+        assert!(report.contains(&format!(
+            "\tMaximum resident set size (kbytes): {}\n",
+            stats.max_rss_kb()
+        )));
+        assert!(report.contains("\tElapsed (wall clock) time (h:mm:ss or m:ss): 0:03.50\n"));
+        assert!(report.ends_with("\tExit status: 3"));
+    }
+
+    #[test]
+    fn elapsed_time_format() {
+        assert_eq!("0:00.00", format_elapsed(0.0));
+        assert_eq!("0:42.50", format_elapsed(42.5));
+        assert_eq!("1:00.00", format_elapsed(59.999));
+        assert_eq!("1:05.25", format_elapsed(65.25));
+        assert_eq!("7:03.50", format_elapsed(423.5));
+        assert_eq!("59:59.99", format_elapsed(3599.99));
+        assert_eq!("1:07:03", format_elapsed(4023.4));
+        assert_eq!("2:00:01", format_elapsed(7201.5));
+    }
+
+    #[test]
+    fn default_report_of_long_run() {
+        let command = vec!["sleep".to_owned(), "1000".to_owned()];
+        let mut stats = stats_fixture(&command);
+        stats.exit_status = ExitStatus::from_raw(0);
+
+        stats.wall_time = Duration::from_millis(59_500);
+        assert!(default_formatter(&stats).ends_with(" cpu 59.500 total"));
+        stats.wall_time = Duration::from_millis(423_500);
+        assert!(default_formatter(&stats).ends_with(" cpu 7:03.50 total"));
+        stats.wall_time = Duration::from_secs(4023);
+        assert!(default_formatter(&stats).ends_with(" cpu 1:07:03 total"));
+
+        // The POSIX report stays in seconds
+        assert!(OutputFormatter::Posix.format_stats(&stats).starts_with("real 4023.00\n"));
+    }
+
+    #[test]
+    fn json_report() {
+        let command = vec!["echo".to_owned(), "say \"hi\"\\\n".to_owned()];
+        let stats = stats_fixture(&command);
+        let report = json_formatter(&stats);
+
+        let fields: Vec<_> = report
+            .trim_start_matches('{')
+            .trim_end_matches('}')
+            .splitn(6, ',')
+            .map(|field| {
+                let mut kv = field.splitn(2, ':');
+                (kv.next().unwrap(), kv.next().unwrap())
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("\"real\"", "3.50"),
+                ("\"user\"", "1.50"),
+                ("\"sys\"", "0.25"),
+                ("\"max_rss_kb\"", stats.max_rss_kb().to_string().as_str()),
+                ("\"exit_code\"", "3"),
+                ("\"command\"", "\"echo say \\\"hi\\\"\\\\\\n\""),
+            ],
+            fields
+        );
+    }
+
+    #[test]
+    fn csv_report() {
+        let command = vec!["sleep".to_owned(), "1".to_owned()];
+        let stats = stats_fixture(&command);
+        let report = csv_formatter(&stats);
+
+        let header: Vec<_> = CSV_HEADER.split(',').collect();
+        let fields: Vec<_> = report.split(',').collect();
+        assert_eq!(header.len(), fields.len());
+        assert_eq!("sleep 1", fields[0]);
+        assert_eq!(3.5, fields[1].parse::<f64>().unwrap());
+        assert_eq!(1.5, fields[2].parse::<f64>().unwrap());
+        assert_eq!(0.25, fields[3].parse::<f64>().unwrap());
+        assert_eq!(stats.max_rss_kb(), fields[4].parse::<u64>().unwrap());
+        assert_eq!(3, fields[5].parse::<i32>().unwrap());
+    }
+
+    #[test]
+    fn csv_escaped_command() {
+        let command = vec!["echo".to_owned(), "a,b \"c\"".to_owned()];
+        let stats = stats_fixture(&command);
+
+        assert!(csv_formatter(&stats).starts_with("\"echo a,b \"\"c\"\"\",3.50,"));
+        assert_eq!("plain", csv_escape("plain"));
+        assert_eq!("\"two\nlines\"", csv_escape("two\nlines"));
+    }
+
+    #[test]
+    fn spread_of_times() {
+        let spread = Spread::of(&[1.0, 2.0, 3.0, 6.0]);
+
+        assert_eq!(Spread { min: 1.0, mean: 3.0, max: 6.0, stddev: 3.5f64.sqrt() }, spread);
+        assert_eq!(0.0, Spread::of(&[2.5]).stddev);
+    }
+
+    #[test]
+    fn summary_report() {
+        let command = vec!["sleep".to_owned(), "1".to_owned()];
+        let mut runs = vec![stats_fixture(&command), stats_fixture(&command)];
+        runs[1].wall_time = Duration::from_millis(4500);
+
+        assert_eq!(
+            "Runs: 2\nreal min 3.50 mean 4.00 max 4.50 stddev 0.50\n\
+             user min 1.50 mean 1.50 max 1.50 stddev 0.00",
+            summary_formatter(&runs)
+        );
+    }
+
+    #[test]
+    fn exit_code_of_repeated_runs() {
+        let command = vec!["true".to_owned()];
+        let mut runs = vec![stats_fixture(&command), stats_fixture(&command)];
+        assert_eq!(3, repeated_exit_code(&runs));
+
+        // A failed run is reported even if the last one succeeded
+        runs[1].exit_status = ExitStatus::from_raw(0);
+        assert_eq!(3, repeated_exit_code(&runs));
+
+        runs[0].exit_status = ExitStatus::from_raw(0);
+        assert_eq!(0, repeated_exit_code(&runs));
+    }
+
+    #[test]
+    fn max_rss_units() {
+        let command = vec!["true".to_owned()];
+        let stats = stats_fixture(&command);
+
+        if cfg!(target_os = "macos") {
+            assert_eq!(2048, stats.max_rss_bytes());
+            assert_eq!(2, stats.max_rss_kb());
+        } else {
+            assert_eq!(2048 * 1024, stats.max_rss_bytes());
+            assert_eq!(2048, stats.max_rss_kb());
+        }
+    }
 }
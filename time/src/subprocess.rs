@@ -1,56 +1,790 @@
 /// Module for creating, and interacting with child processes
-use std::process::{exit, Command, ExitStatus, Stdio};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+#[cfg(target_os = "linux")]
 use std::{
+    os::unix::process::CommandExt,
+    sync::{atomic::AtomicBool, Arc},
+    thread::{self, JoinHandle},
+};
+use std::{
+    fs::File,
     io,
+    sync::atomic::{AtomicI32, Ordering},
     time::{Duration, Instant},
 };
 
-type SubprocessTiming = (ExitStatus, Duration);
+use coreutils_core::{
+    exit::{self, die},
+    libc::{c_int, kill, sighandler_t, signal, SIGINT, SIGTERM, SIG_ERR, SIG_IGN},
+    os::{
+        resource::{get_rusage, ResourceConsumer, RUsage},
+        Pid, TimeVal,
+    },
+};
+#[cfg(target_os = "linux")]
+use coreutils_core::libc::{prctl, setpgid, ECHILD, PR_SET_CHILD_SUBREAPER};
+#[cfg(not(target_os = "fuchsia"))]
+use coreutils_core::os::resource::wait_rusage;
+
+use crate::output::RunStats;
+
+type SubprocessTiming = (ExitStatus, Duration, RUsage);
+
+/// Pid of the running command, that the signals `time` gets are relayed to, or the negated
+/// pid if they are relayed to its process group
+static CHILD_PID: AtomicI32 = AtomicI32::new(0);
+
+/// Last signal relayed to the running command, or 0 if none was since it started
+static RELAYED_SIGNAL: AtomicI32 = AtomicI32::new(0);
 
-/// Wrapper around `std::process::exit` that prints the error's
-/// message to stderr before quitting.
+/// Wrapper around `coreutils_core::exit::die` that prints why `cmd` could not be run to stderr
+/// before quitting.
 ///
 /// Will try to propagate the error code set in the err if available
-pub fn exit_with_msg(err: std::io::Error) -> ! {
-    eprintln!("{}", err);
-
-    // Translate the exit code according to POSIX spec
-    // 1-125: for errors internal to `time`
-    // 126  : Command was found but could not be invoked (PermissionError)
-    // 127  : Command was not found
-    exit(match err.kind() {
-        io::ErrorKind::PermissionDenied => 126,
-        io::ErrorKind::NotFound => 127,
+pub fn exit_with_msg(cmd: &str, err: io::Error) -> ! {
+    die(spawn_error_code(&err), format_args!("cannot run {}: {}", cmd, err))
+}
+
+/// Translate the error of spawning a command to an exit code according to POSIX spec
+/// - 1-125: for errors internal to `time`
+/// - 126  : Command was found but could not be invoked (PermissionError)
+/// - 127  : Command was not found
+fn spawn_error_code(err: &io::Error) -> i32 {
+    match err.kind() {
+        io::ErrorKind::PermissionDenied => exit::CANNOT_INVOKE,
+        io::ErrorKind::NotFound => exit::NOT_FOUND,
         // Translate other error code to 0-124 and shift right by 1
         // Internal exit codes are typically arbitrary enough that they be
         // considered limited to developer use-only
         _ => 1 + (err.raw_os_error().unwrap_or(0) % 125),
-    })
+    }
+}
+
+/// Where an output stream of the command goes, from `--stdout` and `--stderr`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChildOutput {
+    /// The stream of `time`
+    Inherit,
+    /// Discarded, with `/dev/null` or `NUL`, whatever the system calls it
+    Null,
+    /// The file at the path, truncated
+    File(String),
+}
+
+impl ChildOutput {
+    /// Parse the value of `--stdout` or `--stderr`: `inherit`, `/dev/null`, `NUL` or a path
+    pub fn from_arg(arg: &str) -> Self {
+        match arg {
+            "inherit" => ChildOutput::Inherit,
+            "/dev/null" | "NUL" => ChildOutput::Null,
+            path => ChildOutput::File(path.to_owned()),
+        }
+    }
+}
+
+/// Output stream of the command, with its file opened
+#[derive(Debug)]
+enum OpenOutput {
+    Inherit,
+    Null,
+    File(File),
+}
+
+// Deriving it needs `#[default]`, that is newer than the minimum supported version of Rust
+#[allow(clippy::derivable_impls)]
+impl Default for OpenOutput {
+    fn default() -> Self {
+        OpenOutput::Inherit
+    }
+}
+
+impl OpenOutput {
+    fn open(output: &ChildOutput) -> io::Result<Self> {
+        Ok(match output {
+            ChildOutput::Inherit => OpenOutput::Inherit,
+            ChildOutput::Null => OpenOutput::Null,
+            ChildOutput::File(path) => OpenOutput::File(File::create(path).map_err(|err| {
+                io::Error::new(err.kind(), format!("cannot open '{}': {}", path, err))
+            })?),
+        })
+    }
+
+    fn stdio(&self) -> io::Result<Stdio> {
+        Ok(match self {
+            OpenOutput::Inherit => Stdio::inherit(),
+            OpenOutput::Null => Stdio::null(),
+            OpenOutput::File(file) => Stdio::from(file.try_clone()?),
+        })
+    }
+}
+
+/// Standard output and error of the command, opened once for all of its runs, so the output of
+/// every run is kept in the files. Both are inherited by default
+#[derive(Debug, Default)]
+pub struct ChildStdio {
+    stdout: OpenOutput,
+    stderr: OpenOutput,
+}
+
+impl ChildStdio {
+    /// Open the files that the `stdout` and `stderr` of the command go to
+    pub fn open(stdout: &ChildOutput, stderr: &ChildOutput) -> io::Result<Self> {
+        Ok(ChildStdio { stdout: OpenOutput::open(stdout)?, stderr: OpenOutput::open(stderr)? })
+    }
 }
 
 /// Wrapper for creating, spawning and waiting on `std::process::Command`
 /// Returns the `std::process::ExitStatus` of the `std::process::Command`
-/// that was run
-pub fn timed_run(cmd_slice: &[String]) -> io::Result<SubprocessTiming> {
+/// that was run, how long it took, and the resources it used
+///
+/// The standard input of the command is the one of `time`, and its output goes to `stdio`
+///
+/// How long it took is measured with the monotonic clock, so that it is not skewed by the
+/// system clock being adjusted while the command runs
+///
+/// With `child_tree`, the command is run in its own process group and the processes of the
+/// group it did not wait for are waited for too, with their CPU times added to its own
+pub fn timed_run(
+    cmd_slice: &[String], stdio: &ChildStdio, child_tree: bool,
+) -> io::Result<SubprocessTiming> {
     let mut cmd = Command::new(&cmd_slice[0]);
     cmd.args(&cmd_slice[1..]);
-    cmd.stdin(Stdio::inherit()).stdout(Stdio::inherit()).stderr(Stdio::inherit());
+    cmd.stdin(Stdio::inherit()).stdout(stdio.stdout.stdio()?).stderr(stdio.stderr.stdio()?);
+    #[cfg(target_os = "linux")]
+    if child_tree {
+        become_subreaper()?;
+        // Only calls `setpgid`, that is safe to call between `fork` and `exec`
+        unsafe {
+            cmd.pre_exec(|| {
+                if setpgid(0, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
 
     let start_time = Instant::now();
-    let status = cmd.spawn()?.wait()?;
-    Ok((status, start_time.elapsed()))
+    let child = cmd.spawn()?;
+    let previous_handlers = relay_signals_to(&child, child_tree);
+    #[cfg(target_os = "linux")]
+    let (peak_rss, pgid) = (PeakRssPoller::start(child.id() as Pid), child.id() as Pid);
+    #[cfg_attr(not(target_os = "linux"), allow(unused_mut))]
+    let mut waited = wait_child(child);
+    #[cfg(target_os = "linux")]
+    if let (true, Ok((_, usage))) = (child_tree, &mut waited) {
+        if let Err(err) = reap_group(pgid, usage) {
+            waited = Err(err);
+        }
+    }
+    stop_relaying(previous_handlers);
+
+    #[cfg_attr(not(target_os = "linux"), allow(unused_mut))]
+    let (status, mut usage) = waited?;
+    let elapsed = start_time.elapsed();
+    // The kernel may under-report short-lived children, so the peak seen while polling is used
+    // if it is higher. Both are in kilobytes
+    #[cfg(target_os = "linux")]
+    {
+        usage.mem.max_rss = usage.mem.max_rss.max(peak_rss.stop());
+    }
+    Ok((status, elapsed, usage))
+}
+
+/// Make `time` the reaper of its orphaned descendants, so that the processes the command did
+/// not wait for become children of `time` when their parent dies, and can be waited for
+#[cfg(target_os = "linux")]
+fn become_subreaper() -> io::Result<()> {
+    if unsafe { prctl(PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Wait for every process of the process group `pgid` that is a child of `time`, adding their
+/// CPU times to `usage`, until none is left
+#[cfg(target_os = "linux")]
+fn reap_group(pgid: Pid, usage: &mut RUsage) -> io::Result<()> {
+    loop {
+        match wait_rusage(-pgid) {
+            Ok((_, reaped)) => {
+                let timing = &mut usage.timing;
+                timing.user_time = add_timevals(timing.user_time, reaped.timing.user_time);
+                timing.sys_time = add_timevals(timing.sys_time, reaped.timing.sys_time);
+                usage.mem.max_rss = usage.mem.max_rss.max(reaped.mem.max_rss);
+            },
+            Err(err) if err.raw_os_error() == Some(ECHILD) => break Ok(()),
+            Err(err) => break Err(err),
+        }
+    }
+}
+
+/// Sum of the times `a` and `b`
+#[cfg(target_os = "linux")]
+fn add_timevals(a: TimeVal, b: TimeVal) -> TimeVal {
+    let usec = a.tv_usec + b.tv_usec;
+    TimeVal { tv_sec: a.tv_sec + b.tv_sec + usec / 1_000_000, tv_usec: usec % 1_000_000 }
+}
+
+/// Poller of the peak resident set size of a running process, from the `VmHWM` field of
+/// `/proc/<pid>/status`
+#[cfg(target_os = "linux")]
+struct PeakRssPoller {
+    done: Arc<AtomicBool>,
+    poller: JoinHandle<u64>,
+}
+
+#[cfg(target_os = "linux")]
+impl PeakRssPoller {
+    /// How long to wait between two reads of the status of the process
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    /// Start polling the peak resident set size of the process `pid`
+    fn start(pid: Pid) -> Self {
+        let done = Arc::new(AtomicBool::new(false));
+        let polling = Arc::clone(&done);
+        let poller = thread::spawn(move || {
+            let mut peak = 0;
+            while !polling.load(Ordering::SeqCst) {
+                if let Some(kb) = proc_peak_rss_kb(pid) {
+                    peak = peak.max(kb);
+                }
+                thread::sleep(Self::INTERVAL);
+            }
+            peak
+        });
+
+        PeakRssPoller { done, poller }
+    }
+
+    /// Stop polling, once the process is reaped, and return the highest peak resident set size
+    /// seen in kilobytes, or 0 if the status of the process could not be read
+    fn stop(self) -> u64 {
+        self.done.store(true, Ordering::SeqCst);
+        self.poller.join().unwrap_or(0)
+    }
+}
+
+/// Peak resident set size in kilobytes of the process `pid`, if it's still running
+#[cfg(target_os = "linux")]
+fn proc_peak_rss_kb(pid: Pid) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    parse_vm_hwm(&status)
+}
+
+/// Parse the `VmHWM` field, in kilobytes, of the `status` of a process
+#[cfg(target_os = "linux")]
+fn parse_vm_hwm(status: &str) -> Option<u64> {
+    let field = status.lines().find_map(|line| line.strip_prefix("VmHWM:"))?;
+    field.trim().strip_suffix("kB")?.trim().parse().ok()
+}
+
+/// Run the command in `cmd_slice` `count` times, one after the other, and return the stats of
+/// every run
+///
+/// If a `SIGINT` or `SIGTERM` is relayed to a run, or it is killed by a `SIGINT` that `time`
+/// ignored, the runs left are not started, and the stats of the runs done so far are returned
+pub fn timed_runs<'a>(
+    cmd_slice: &'a [String], count: usize, stdio: &ChildStdio, child_tree: bool,
+) -> io::Result<Vec<RunStats<'a>>> {
+    let mut runs = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (exit_status, wall_time, rusage) = timed_run(cmd_slice, stdio, child_tree)?;
+        runs.push(RunStats { command: cmd_slice, exit_status, wall_time, rusage });
+        if RELAYED_SIGNAL.load(Ordering::SeqCst) != 0 || exit_status.signal() == Some(SIGINT) {
+            break;
+        }
+    }
+    Ok(runs)
+}
+
+/// Smallest durations that can be measured, found by calibration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resolution {
+    /// Step of the monotonic clock the wall time is measured with
+    pub clock: Duration,
+    /// Step of the CPU times reported by `getrusage`
+    pub cpu: Duration,
+}
+
+impl Resolution {
+    /// Longest an empty loop spins waiting for the CPU times to change, in case they never do,
+    /// which is also the coarsest resolution that can be found
+    const MAX_SPIN: Duration = Duration::from_millis(100);
+
+    /// Find the resolution of the clocks, spinning in empty loops until they change
+    pub fn calibrate() -> Self {
+        let start = Instant::now();
+        let clock = loop {
+            let now = Instant::now();
+            if now > start {
+                break now - start;
+            }
+        };
+
+        // The first change may come anywhere in a step, so the step is the time to the second
+        let spin_start = Instant::now();
+        let first = own_cpu_time();
+        let mut changed = first;
+        while changed == first && spin_start.elapsed() < Self::MAX_SPIN {
+            changed = own_cpu_time();
+        }
+        let mut next = changed;
+        while next == changed && spin_start.elapsed() < Self::MAX_SPIN {
+            next = own_cpu_time();
+        }
+        let cpu = if next > changed { next - changed } else { Self::MAX_SPIN };
+
+        Resolution { clock, cpu }
+    }
+
+    /// Whether the timing in `stats` is too short to be told apart from the resolution
+    pub fn is_too_fast(&self, stats: &RunStats) -> bool {
+        stats.wall_time < self.clock.max(self.cpu)
+    }
+
+    /// Whether the timing in `stats` is short enough that it could be too fast for some
+    /// resolution, without the cost of calibrating
+    pub fn may_be_too_fast(stats: &RunStats) -> bool {
+        stats.wall_time < Self::MAX_SPIN
+    }
+}
+
+/// User and system CPU time used so far by `time` itself
+fn own_cpu_time() -> Duration {
+    let timing = get_rusage(ResourceConsumer::Caller).timing;
+    timeval_duration(timing.user_time) + timeval_duration(timing.sys_time)
+}
+
+/// Convert `tv` into a `Duration`
+fn timeval_duration(tv: TimeVal) -> Duration {
+    Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000)
+}
+
+/// Relay the `SIGTERM` that `time` gets to `child`, so it is not orphaned if `time` is
+/// terminated, and is still reported on once it dies
+///
+/// With `to_group`, it and `SIGINT` are relayed to the process group of `child`, that it leads.
+/// Otherwise `child` is in the process group of `time` and already gets the `SIGINT` of the
+/// terminal, so `time` ignores it like GNU time does, not to deliver it twice
+///
+/// Returns the handlers of the signals that were replaced, to give to [`stop_relaying`]
+fn relay_signals_to(child: &Child, to_group: bool) -> [sighandler_t; 2] {
+    let pid = child.id() as Pid;
+    RELAYED_SIGNAL.store(0, Ordering::SeqCst);
+    CHILD_PID.store(if to_group { -pid } else { pid }, Ordering::SeqCst);
+    let relay = relay_signal as extern "C" fn(c_int) as sighandler_t;
+    let on_interrupt = if to_group { relay } else { SIG_IGN };
+    unsafe { [signal(SIGINT, on_interrupt), signal(SIGTERM, relay)] }
+}
+
+/// Stop relaying signals once the command is reaped, putting back the `previous` handlers of
+/// `SIGINT` and `SIGTERM`, so that `time` is interrupted by them again
+fn stop_relaying(previous: [sighandler_t; 2]) {
+    CHILD_PID.store(0, Ordering::SeqCst);
+    for (&signum, &handler) in [SIGINT, SIGTERM].iter().zip(&previous) {
+        if handler != SIG_ERR {
+            unsafe { signal(signum, handler) };
+        }
+    }
+}
+
+/// Signal handler delivering `signum` to the running command, or to its process group
+extern "C" fn relay_signal(signum: c_int) {
+    RELAYED_SIGNAL.store(signum, Ordering::SeqCst);
+    let pid = CHILD_PID.load(Ordering::SeqCst);
+    if pid != 0 {
+        unsafe { kill(pid, signum) };
+    }
+}
+
+/// Reap `child`, getting the resources used only by it
+#[cfg(not(target_os = "fuchsia"))]
+fn wait_child(child: Child) -> io::Result<(ExitStatus, RUsage)> {
+    let (status, usage) = wait_rusage(child.id() as Pid)?;
+    Ok((ExitStatus::from_raw(status), usage))
+}
+
+/// Reap `child`, getting the resources used by all of our children
+#[cfg(target_os = "fuchsia")]
+fn wait_child(mut child: Child) -> io::Result<(ExitStatus, RUsage)> {
+    let status = child.wait()?;
+    Ok((status, get_rusage(ResourceConsumer::Children)))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::timed_run;
+    use std::{
+        env, fs, panic, process,
+        sync::atomic::Ordering,
+        thread,
+        time::Duration,
+    };
+
+    use coreutils_core::libc::{
+        _exit, c_int, fork, getpid, kill, setpgid, sighandler_t, signal, waitpid, SIGINT, SIGTERM,
+        SIG_DFL, WEXITSTATUS, WIFEXITED,
+    };
+
+    // Signals are relayed to a single running command, so commands are run one at a time,
+    // holding the lock of the shared state
+    use coreutils_core::testutil::lock_shared_state;
+
+    use super::{
+        relay_signal, spawn_error_code, timed_run, timed_runs, ChildOutput, ChildStdio,
+        Resolution, RELAYED_SIGNAL,
+    };
+    #[cfg(target_os = "linux")]
+    use coreutils_core::os::resource::RUsage;
+    use crate::output::{default_formatter, summary_formatter, RunStats};
+
+    #[test]
+    fn cpu_bound_command_has_user_time() {
+        let cmd = ["sh", "-c", "i=0; while [ $i -lt 300000 ]; do i=$((i + 1)); done"];
+        let cmd: Vec<_> = cmd.iter().map(|s| s.to_string()).collect();
+
+        let _run = lock_shared_state();
+        let (status, _, usage) = timed_run(&cmd, &ChildStdio::default(), false).unwrap();
+        let user_time = usage.timing.user_time;
+
+        assert!(status.success());
+        assert!(user_time.tv_sec > 0 || user_time.tv_usec > 0);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn child_tree_includes_orphaned_grandchild() {
+        // The grandchild spins in the background, and its parent exits without waiting for it
+        let script = "sh -c 'i=0; while [ $i -lt 300000 ]; do i=$((i + 1)); done' & exit 0";
+        let cmd = ["sh", "-c", script];
+        let cmd: Vec<_> = cmd.iter().map(|s| s.to_string()).collect();
+        let secs = |usage: &RUsage| {
+            usage.timing.user_time.tv_sec as f64 + usage.timing.user_time.tv_usec as f64 / 1e6
+        };
+
+        let _run = lock_shared_state();
+        let (_, _, usage) = timed_run(&cmd, &ChildStdio::default(), false).unwrap();
+        let child_only = secs(&usage);
+
+        let (status, wall_time, usage) = timed_run(&cmd, &ChildStdio::default(), true).unwrap();
+        let tree = secs(&usage);
+
+        assert!(status.success());
+        assert!(tree > 0.05, "user time of the tree: {}", tree);
+        assert!(tree > child_only * 2.0, "{} is not above {}", tree, child_only);
+        // Its wall time includes waiting for the grandchild
+        assert!(wall_time.as_secs_f64() >= tree * 0.5);
+    }
+
+    #[test]
+    fn cpu_bound_command_has_high_cpu_percent() {
+        let cmd = ["sh", "-c", "i=0; while [ $i -lt 300000 ]; do i=$((i + 1)); done"];
+        let cmd: Vec<_> = cmd.iter().map(|s| s.to_string()).collect();
+
+        let _run = lock_shared_state();
+        let (exit_status, wall_time, rusage) =
+            timed_run(&cmd, &ChildStdio::default(), false).unwrap();
+        let stats = RunStats { command: &cmd, exit_status, wall_time, rusage };
+
+        assert!(stats.cpu_percent().unwrap() > 50.0);
+    }
+
+    #[test]
+    fn instant_command_has_cpu_percent() {
+        let cmd = vec!["true".to_string()];
+
+        let _run = lock_shared_state();
+        let (exit_status, wall_time, rusage) =
+            timed_run(&cmd, &ChildStdio::default(), false).unwrap();
+        let stats = RunStats { command: &cmd, exit_status, wall_time, rusage };
+
+        assert!(stats.cpu_percent_str().ends_with('%'));
+    }
+
+    #[test]
+    fn default_report_of_sleep() {
+        let cmd = vec!["sleep".to_string(), "0.2".to_string()];
+
+        let _run = lock_shared_state();
+        let (exit_status, wall_time, rusage) =
+            timed_run(&cmd, &ChildStdio::default(), false).unwrap();
+        let stats = RunStats { command: &cmd, exit_status, wall_time, rusage };
+        let report = default_formatter(&stats);
+
+        let fields: Vec<_> = report.split(' ').collect();
+        assert_eq!(8, fields.len(), "{}", report);
+        let labels = [fields[1], fields[3], fields[5], fields[7]];
+        assert_eq!(["user", "system", "cpu", "total"], labels);
+        assert!(fields[0].ends_with('s') && fields[2].ends_with('s') && fields[4].ends_with('%'));
+        // A sleeping command uses almost no CPU
+        assert!(fields[4].trim_end_matches('%').parse::<f64>().unwrap() < 50.0);
+
+        let total: f64 = fields[6].parse().unwrap();
+        assert!((0.2..1.0).contains(&total));
+        assert_eq!(3, fields[6].split('.').nth(1).unwrap().len());
+    }
+
+    #[test]
+    fn calibrated_resolution() {
+        let resolution = Resolution::calibrate();
+
+        assert!(resolution.clock > Duration::from_secs(0));
+        assert!(resolution.cpu > Duration::from_secs(0));
+        assert!(resolution.cpu <= Resolution::MAX_SPIN);
+    }
+
+    #[test]
+    fn instant_command_is_too_fast_for_coarse_clock() {
+        let cmd = vec!["true".to_string()];
+        // As if the clocks had a step of a second
+        let coarse = Resolution { clock: Duration::from_secs(1), cpu: Duration::from_secs(1) };
+
+        let _run = lock_shared_state();
+        let (exit_status, wall_time, rusage) =
+            timed_run(&cmd, &ChildStdio::default(), false).unwrap();
+        let stats = RunStats { command: &cmd, exit_status, wall_time, rusage };
+        assert!(Resolution::may_be_too_fast(&stats));
+        assert!(coarse.is_too_fast(&stats));
+
+        let fine = Resolution { clock: Duration::from_nanos(1), cpu: Duration::from_nanos(1) };
+        assert!(!fine.is_too_fast(&stats));
+    }
+
+    #[test]
+    fn sleeping_command_is_not_too_fast() {
+        let cmd = ["sleep", "0.2"];
+        let cmd: Vec<_> = cmd.iter().map(|s| s.to_string()).collect();
+        let resolution = Resolution::calibrate();
+
+        let _run = lock_shared_state();
+        let (exit_status, wall_time, rusage) =
+            timed_run(&cmd, &ChildStdio::default(), false).unwrap();
+        let stats = RunStats { command: &cmd, exit_status, wall_time, rusage };
+        assert!(!Resolution::may_be_too_fast(&stats));
+        assert!(!resolution.is_too_fast(&stats));
+    }
+
+    #[test]
+    fn exit_status_of_failing_command() {
+        let cmd = ["sh", "-c", "exit 3"];
+        let cmd: Vec<_> = cmd.iter().map(|s| s.to_string()).collect();
+
+        let _run = lock_shared_state();
+        let (status, ..) = timed_run(&cmd, &ChildStdio::default(), false).unwrap();
+        assert_eq!(Some(3), status.code());
+    }
+
+    #[test]
+    fn exit_status_of_killed_command() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let cmd = ["sh", "-c", "kill -9 $$"];
+        let cmd: Vec<_> = cmd.iter().map(|s| s.to_string()).collect();
+
+        let _run = lock_shared_state();
+        let (exit_status, wall_time, rusage) =
+            timed_run(&cmd, &ChildStdio::default(), false).unwrap();
+        assert_eq!(None, exit_status.code());
+        assert_eq!(Some(9), exit_status.signal());
+
+        let stats = RunStats { command: &cmd, exit_status, wall_time, rusage };
+        assert!(default_formatter(&stats).ends_with(" total\nsignal 9"));
+    }
 
     #[test]
     fn invalid_command_returns_errno_when_set() {
-        if let Err(err) = timed_run(&["does-not-exist".to_string()]) {
+        let cmd = ["does-not-exist".to_string()];
+        if let Err(err) = timed_run(&cmd, &ChildStdio::default(), false) {
             assert!(err.raw_os_error() == Some(2))
         } else {
             panic!("Subprocess did not fail as expected")
         }
     }
+
+    #[test]
+    fn missing_command_exits_127() {
+        let cmd = ["does-not-exist".to_string()];
+        let err = timed_run(&cmd, &ChildStdio::default(), false).unwrap_err();
+        assert_eq!(127, spawn_error_code(&err));
+    }
+
+    #[test]
+    fn not_executable_command_exits_126() {
+        let err = timed_run(&["/".to_string()], &ChildStdio::default(), false).unwrap_err();
+        assert_eq!(126, spawn_error_code(&err));
+    }
+
+    #[test]
+    fn sigterm_is_relayed_to_command() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let _run = lock_shared_state();
+        let relay = thread::spawn(|| {
+            thread::sleep(Duration::from_millis(200));
+            unsafe { kill(getpid(), SIGTERM) };
+        });
+
+        let cmd = vec!["sleep".to_string(), "5".to_string()];
+        let (status, elapsed, _) = timed_run(&cmd, &ChildStdio::default(), false).unwrap();
+        relay.join().unwrap();
+
+        assert_eq!(Some(SIGTERM), status.signal());
+        assert!(elapsed < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn sigterm_stops_repeated_runs() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let _run = lock_shared_state();
+        let relay = thread::spawn(|| {
+            thread::sleep(Duration::from_millis(200));
+            unsafe { kill(getpid(), SIGTERM) };
+        });
+
+        let cmd = vec!["sleep".to_string(), "1".to_string()];
+        let runs = timed_runs(&cmd, 5, &ChildStdio::default(), false).unwrap();
+        relay.join().unwrap();
+
+        assert_eq!(1, runs.len());
+        assert_eq!(Some(SIGTERM), runs[0].exit_status.signal());
+    }
+
+    #[test]
+    fn sigint_to_the_group_reaches_command_once() {
+        use std::os::unix::process::ExitStatusExt;
+
+        // Sends a `SIGINT` to its whole process group, like a Ctrl-C in a terminal does
+        let cmd = ["sh", "-c", "sleep 0.1; kill -INT 0; sleep 1"];
+        let cmd: Vec<_> = cmd.iter().map(|s| s.to_string()).collect();
+
+        let _run = lock_shared_state();
+        // The process group of the tests is the one of cargo, so the command is run by a child
+        // process leading a group of its own
+        match unsafe { fork() } {
+            0 => {
+                unsafe { setpgid(0, 0) };
+                let run = panic::catch_unwind(|| timed_run(&cmd, &ChildStdio::default(), false));
+                // How many times the command got the signal, once from the group and once more
+                // if it was relayed
+                let delivered = match run {
+                    Ok(Ok((status, ..))) if status.signal() == Some(SIGINT) => {
+                        1 + (RELAYED_SIGNAL.load(Ordering::SeqCst) == SIGINT) as c_int
+                    },
+                    _ => 0,
+                };
+                unsafe { _exit(delivered) }
+            },
+            pid => {
+                let mut status = 0;
+                unsafe { waitpid(pid, &mut status, 0) };
+                assert!(WIFEXITED(status));
+                assert_eq!(1, WEXITSTATUS(status));
+            },
+        }
+    }
+
+    #[test]
+    fn signal_handlers_are_restored_after_run() {
+        let _run = lock_shared_state();
+        let cmd = vec!["true".to_string()];
+        timed_run(&cmd, &ChildStdio::default(), false).unwrap();
+
+        // Swapped with the default handler to see the current one, and put back
+        let current = unsafe { signal(SIGTERM, SIG_DFL) };
+        unsafe { signal(SIGTERM, current) };
+        assert_ne!(relay_signal as extern "C" fn(c_int) as sighandler_t, current);
+    }
+
+    #[test]
+    fn elapsed_time_of_sleeping_command() {
+        let _run = lock_shared_state();
+        let cmd = vec!["sleep".to_string(), "0.3".to_string()];
+        let (status, elapsed, _) = timed_run(&cmd, &ChildStdio::default(), false).unwrap();
+
+        assert!(status.success());
+        assert!(elapsed >= Duration::from_millis(300));
+        assert!(elapsed < Duration::from_millis(1300));
+    }
+
+    #[test]
+    fn repeated_command_has_summary() {
+        let cmd = vec!["true".to_string()];
+
+        let _run = lock_shared_state();
+        let runs = timed_runs(&cmd, 3, &ChildStdio::default(), false).unwrap();
+        let summary = summary_formatter(&runs);
+
+        assert_eq!(3, runs.len());
+        assert!(runs.iter().all(|run| run.exit_status.success()));
+        assert!(summary.starts_with("Runs: 3\nreal min "));
+        assert!(summary.contains("\nuser min "));
+    }
+
+    #[test]
+    fn output_values() {
+        assert_eq!(ChildOutput::Inherit, ChildOutput::from_arg("inherit"));
+        assert_eq!(ChildOutput::Null, ChildOutput::from_arg("/dev/null"));
+        assert_eq!(ChildOutput::Null, ChildOutput::from_arg("NUL"));
+        assert_eq!(ChildOutput::File("out.log".to_owned()), ChildOutput::from_arg("out.log"));
+    }
+
+    #[test]
+    fn output_redirected_to_file() {
+        let path = env::temp_dir().join(format!("time-stdout-{}.log", process::id()));
+        let stdout = ChildOutput::File(path.to_str().unwrap().to_owned());
+        let stdio = ChildStdio::open(&stdout, &ChildOutput::Inherit).unwrap();
+        let cmd = ["sh", "-c", "echo out; echo err >&2"];
+        let cmd: Vec<_> = cmd.iter().map(|s| s.to_string()).collect();
+
+        let _run = lock_shared_state();
+        let runs = timed_runs(&cmd, 2, &stdio, false).unwrap();
+        drop(stdio);
+
+        // The output of every run is kept
+        assert_eq!("out\nout\n", fs::read_to_string(&path).unwrap());
+        assert!(runs.iter().all(|run| run.exit_status.success()));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn output_redirected_to_null_is_still_timed() {
+        let stdio = ChildStdio::open(&ChildOutput::Null, &ChildOutput::Null).unwrap();
+        let cmd = ["sh", "-c", "echo out; echo err >&2; sleep 0.1"];
+        let cmd: Vec<_> = cmd.iter().map(|s| s.to_string()).collect();
+
+        let _run = lock_shared_state();
+        let (status, elapsed, _) = timed_run(&cmd, &stdio, false).unwrap();
+
+        assert!(status.success());
+        assert!(elapsed >= Duration::from_millis(100));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_peak_rss_of_status() {
+        let status = "Name:\tsh\nVmPeak:\t    2580 kB\nVmHWM:\t    1024 kB\nVmRSS:\t 980 kB\n";
+        assert_eq!(Some(1024), super::parse_vm_hwm(status));
+        assert_eq!(None, super::parse_vm_hwm("Name:\tsh\n"));
+        assert_eq!(None, super::parse_vm_hwm("VmHWM:\t1024 MB\n"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn memory_allocating_command_has_peak_rss() {
+        // `dd` allocates and fills a buffer of the block size
+        let cmd = ["dd", "if=/dev/zero", "of=/dev/null", "bs=64M", "count=1", "status=none"];
+        let cmd: Vec<_> = cmd.iter().map(|s| s.to_string()).collect();
+
+        let _run = lock_shared_state();
+        let (exit_status, wall_time, rusage) =
+            timed_run(&cmd, &ChildStdio::default(), false).unwrap();
+        let stats = RunStats { command: &cmd, exit_status, wall_time, rusage };
+
+        assert!(stats.exit_status.success());
+        assert!(stats.max_rss_bytes() >= 64 << 20);
+        assert!(stats.max_rss_bytes() < 1 << 40);
+    }
 }
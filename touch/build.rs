@@ -16,9 +16,9 @@ fn main() {
         },
     };
 
-    app.gen_completions("dirname", Shell::Zsh, out_dir.clone());
-    app.gen_completions("dirname", Shell::Fish, out_dir.clone());
-    app.gen_completions("dirname", Shell::Bash, out_dir.clone());
-    app.gen_completions("dirname", Shell::PowerShell, out_dir.clone());
-    app.gen_completions("dirname", Shell::Elvish, out_dir);
+    app.gen_completions("touch", Shell::Zsh, out_dir.clone());
+    app.gen_completions("touch", Shell::Fish, out_dir.clone());
+    app.gen_completions("touch", Shell::Bash, out_dir.clone());
+    app.gen_completions("touch", Shell::PowerShell, out_dir.clone());
+    app.gen_completions("touch", Shell::Elvish, out_dir);
 }
@@ -14,11 +14,12 @@ pub(crate) fn create_app<'a, 'b>() -> App<'a, 'b> {
         .arg(
             Arg::with_name("FILE")
                 .help(
-                    "File(s) to create empty if it does not exist, unless -c or -h is supplied, \
-                     or '-' to modify the standard input.",
+                    "File(s) to create empty if it does not exist, unless -c is supplied, or '-' \
+                     to modify the standard input.",
                 )
                 .multiple(true)
-                .required(true),
+                .required_unless("files0_from")
+                .conflicts_with("files0_from"),
         )
         .arg(
             Arg::with_name("accesstime")
@@ -60,11 +61,6 @@ pub(crate) fn create_app<'a, 'b>() -> App<'a, 'b> {
                     "If the file is a symbolic link, change the times of the link itself rather \
                      than the file that the link points to.",
                 )
-                .long_help(
-                    "If the file is a symbolic link, change the times of the link itself rather \
-                     than the file that the link points to.\n\nNote that -h implies -c and thus \
-                     will not create any new files.",
-                )
                 .long("no-deref")
                 .visible_alias("no-dereference")
                 .short("h"),
@@ -79,17 +75,27 @@ pub(crate) fn create_app<'a, 'b>() -> App<'a, 'b> {
                 )
                 .long("time")
                 .short("T")
-                .value_name("WORD")
-                .possible_values(&["access", "atime", "modify", "mtime", "use"]),
+                .value_name("WORD"),
         )
         .arg(
             Arg::with_name("date")
-                .help(
-                    "Parse STRING (date format [Y-M-D h:m:s]) and use it instead of current time.",
+                .help("Parse STRING and use it instead of current time.")
+                .long_help(
+                    "Parse STRING and use it instead of current time.\n\n\
+                    STRING can be any of:\n\t\
+                    @SECONDS[.frac]              seconds since the Unix epoch\n\t\
+                    Y-M-DTh:m:s[.frac]+hh:mm     date and time with an UTC offset\n\t\
+                    Y-M-D h:m:s[.frac]           local date and time\n\t\
+                    Y-M-DTh:m:s[.frac]           local date and time\n\t\
+                    Y-M-D                        midnight of the local date\n\t\
+                    [+|-]NUMBER UNIT...          relative to OTHER_FILE times, or to now\n\n\
+                    Local times use the timezone in the TZ environment variable if set.\n\n\
+                    A relative STRING has one or more NUMBER UNIT items, like '-1 hour' or \
+                    '+1 day 30 minutes', where UNIT can be second, minute, hour, day or week.",
                 )
                 .long("date")
                 .short("d")
-                .conflicts_with("reference")
+                .allow_hyphen_values(true)
                 .value_name("STRING"),
         )
         .arg(
@@ -103,4 +109,26 @@ pub(crate) fn create_app<'a, 'b>() -> App<'a, 'b> {
                 .conflicts_with("reference")
                 .value_name("STRING"),
         )
+        .arg(
+            Arg::with_name("files0_from")
+                .help(
+                    "Read the files to touch from the NUL-terminated names in FILE, or from \
+                     standard input if FILE is '-'.",
+                )
+                .long("files0-from")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .help("Touch the files with N threads, instead of one at a time.")
+                .long("jobs")
+                .short("j")
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .help("Print a line to the standard output for each file created or touched.")
+                .long("verbose")
+                .short("v"),
+        )
 }
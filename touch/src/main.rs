@@ -1,55 +1,301 @@
 use std::{
-    fs::{self, File, Metadata},
-    process,
+    error,
+    ffi::CString,
+    fmt,
+    fs::{self, File, Metadata, OpenOptions},
+    io::{self, Read, Write},
+    os::unix::fs::OpenOptionsExt,
+    process, thread,
     time::SystemTime,
 };
 
 use clap::ArgMatches;
+use coreutils_core::{
+    exit::{self, die},
+    libc::{
+        timespec, utimensat, AT_FDCWD, AT_SYMLINK_NOFOLLOW, ENOTSUP, EOPNOTSUPP, O_NOFOLLOW,
+        UTIME_OMIT,
+    },
+};
 use filetime::{set_file_atime, set_file_mtime, set_file_times, set_symlink_file_times, FileTime};
-use time::PrimitiveDateTime;
+use time::{Date, Duration, Format, OffsetDateTime, PrimitiveDateTime, UtcOffset};
 
 mod cli;
-// TODO: add Unit tests for touch
 #[cfg(test)]
 mod tests;
 
 fn main() {
     let matches = cli::create_app().get_matches();
 
-    let flags = TouchFlags::from_matches(&matches);
+    let flags = TouchFlags::from_matches(&matches).unwrap_or_else(|err| die(exit::FAILURE, err));
+
+    let files0;
+    let files: Vec<_> = match matches.value_of("files0_from") {
+        Some(source) => {
+            files0 = read_files0_from(source).unwrap_or_else(|err| die(exit::FAILURE, err));
+            files0.iter().map(String::as_str).collect()
+        },
+        // get files list in argument
+        // Required argument if there is no `--files0-from`, ok to unwrap and not check if is
+        // supplied.
+        None => matches.values_of("FILE").unwrap().collect(),
+    };
+
+    let exit_code = touch(&files, flags, &mut io::stdout().lock());
+
+    if exit_code != 0 {
+        process::exit(exit_code);
+    }
+}
 
-    // get files list in argument
-    // Required argument, ok to unwrap and not check if is supplied.
-    let files = matches.values_of("FILE").unwrap();
+/// Read the NUL-terminated file names in `source`, or in the standard input if `source` is
+/// `-`.
+fn read_files0_from(source: &str) -> Result<Vec<String>, String> {
+    let res = if source == "-" {
+        parse_files0(io::stdin().lock())
+    } else {
+        File::open(source).and_then(parse_files0)
+    };
 
-    touch(&files.collect::<Vec<_>>(), flags);
+    res.map_err(|err| format!("cannot read file names from '{}': {}", source, err))
 }
 
-fn touch(files: &[&str], flags: TouchFlags) {
-    let (new_atime, new_mtime) = new_filetimes(flags).unwrap_or_else(|err| {
-        eprintln!("touch: {}", err);
-        process::exit(1);
-    });
+/// Parse the NUL-terminated file names read from `reader`.
+///
+/// The last name does not need to be terminated. An empty input has no names.
+fn parse_files0(mut reader: impl Read) -> io::Result<Vec<String>> {
+    let mut input = Vec::new();
+    reader.read_to_end(&mut input)?;
 
-    for filename in files {
-        // if file already exist in the current directory
-        let mut file_metadata =
-            if flags.no_deref { fs::symlink_metadata(&filename) } else { fs::metadata(&filename) };
+    if input.last() == Some(&b'\0') {
+        input.pop();
+    }
+    if input.is_empty() {
+        return Ok(Vec::new());
+    }
 
-        if file_metadata.is_err() && !flags.no_create {
-            match File::create(&filename) {
-                Ok(_) => {
-                    file_metadata = if flags.no_deref {
-                        fs::symlink_metadata(&filename)
-                    } else {
-                        fs::metadata(&filename)
-                    };
-                },
-                Err(e) => eprintln!("touch: Failed to create file {}: {}", &filename, e),
+    input
+        .split(|&byte| byte == b'\0')
+        .map(|name| {
+            if name.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid zero-length file name",
+                ));
             }
+
+            String::from_utf8(name.to_vec())
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        })
+        .collect()
+}
+
+/// Touch all `files` acording with the `flags`, reporting each file touched to `out` if
+/// `--verbose` is set.
+///
+/// Every file is processed even if some of them fail. With more than one job, the `files` are
+/// split between that many threads, and the files are still reported in the order of the
+/// `files`. Returns the exit code, which is 1 if any file could not be created or have its
+/// times updated, and 0 otherwise.
+fn touch(files: &[&str], flags: TouchFlags, out: &mut dyn Write) -> i32 {
+    let (new_atime, new_mtime) =
+        new_filetimes(flags).unwrap_or_else(|err| die(exit::FAILURE, err));
+
+    let results: Vec<_> = if flags.jobs > 1 && files.len() > 1 {
+        // `usize::div_ceil` is newer than the minimum supported version of Rust
+        #[allow(clippy::manual_div_ceil)]
+        let chunk_size = (files.len() + flags.jobs - 1) / flags.jobs;
+        let worker_flags = flags.without_time_sources();
+        let workers: Vec<_> = files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let chunk: Vec<String> = chunk.iter().map(|&file| file.to_owned()).collect();
+                thread::spawn(move || -> Vec<io::Result<Touched>> {
+                    chunk
+                        .iter()
+                        .map(|file| touch_one(file, new_atime, new_mtime, worker_flags))
+                        .collect()
+                })
+            })
+            .collect();
+        workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+    } else {
+        files.iter().map(|filename| touch_one(filename, new_atime, new_mtime, flags)).collect()
+    };
+
+    let mut exit_code = 0;
+    let mut unsupported_reported = false;
+
+    for (filename, result) in files.iter().zip(results) {
+        match result {
+            Ok(touched) if flags.verbose => {
+                if let Some(line) = touched.message(filename) {
+                    if let Err(err) = writeln!(out, "touch: {}", line) {
+                        eprintln!("touch: write error: {}", err);
+                        exit_code = 1;
+                    }
+                }
+            },
+            Ok(_) => {},
+            Err(err) => {
+                if let Some(line) = error_message(&err, flags, &mut unsupported_reported) {
+                    eprintln!("touch: {}", line);
+                }
+                exit_code = 1;
+            },
         }
-        if let Ok(file_metadata) = file_metadata {
-            update_time(&filename, new_atime, new_mtime, &file_metadata, flags);
+    }
+
+    exit_code
+}
+
+/// Message to report for the error `err` of touching a file acording with the `flags`.
+///
+/// A platform that can't update the times of symbolic links fails the same way for every file
+/// with `-h`, so this is told only once, when `unsupported_reported` is not already set.
+fn error_message(
+    err: &io::Error, flags: TouchFlags, unsupported_reported: &mut bool,
+) -> Option<String> {
+    let code = os_error(err);
+    if flags.no_deref && (code == Some(ENOTSUP) || code == Some(EOPNOTSUPP)) {
+        if *unsupported_reported {
+            return None;
+        }
+        *unsupported_reported = true;
+        return Some("updating symlink times is not supported on this platform".to_owned());
+    }
+
+    Some(err.to_string())
+}
+
+/// What was done to a file by [`touch_one`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Touched {
+    /// The file was created, with the new times.
+    Created,
+    /// The file already existed and has the new times.
+    Updated,
+    /// The file does not exist and was not created, because of `-c`.
+    Skipped,
+}
+
+impl Touched {
+    /// The line of `--verbose` for the file at `path`, if something was done to it.
+    fn message(self, path: &str) -> Option<String> {
+        match self {
+            Touched::Created => Some(format!("created '{}'", path)),
+            Touched::Updated => Some(format!("setting times of '{}'", path)),
+            Touched::Skipped => None,
+        }
+    }
+}
+
+/// Create the file at `path` if needed and set its times to `new_atime` and `new_mtime`
+/// acording with the `flags`, returning what was done.
+///
+/// # Errors
+/// If the file cannot be created or its times cannot be updated, an error variant is
+/// returned. Its message tells the `path` and which of the operations failed.
+fn touch_one(
+    path: &str, new_atime: FileTime, new_mtime: FileTime, flags: TouchFlags,
+) -> io::Result<Touched> {
+    let touched = match file_metadata(path, flags.no_deref) {
+        Ok(_) => Touched::Updated,
+        // The file does not exist and we are not allowed to create it, so there is nothing to do
+        // with it.
+        Err(_) if flags.no_create => return Ok(Touched::Skipped),
+        // A path ending with a `/` can only be a directory, which is never created
+        Err(err) if path.ends_with('/') => {
+            return Err(with_context(err, format!("cannot touch '{}'", path)));
+        },
+        Err(_) => {
+            create_file(path, flags.no_deref)
+                .map_err(|err| with_context(err, format!("Failed to create file {}", path)))?;
+            Touched::Created
+        },
+    };
+
+    update_time(path, new_atime, new_mtime, flags)?;
+    Ok(touched)
+}
+
+/// Create an empty file at `path`, leaving it untouched if it already exists.
+///
+/// If `no_deref` is set, a symbolic link at `path` is not followed, so the file it points to is
+/// never created even if the link is placed after checking that `path` does not exist.
+///
+/// As with GNU touch, a new file is created with the mode `0o666` less the bits of the umask,
+/// for example `0o644` with the usual umask `0o022`.
+fn create_file(path: &str, no_deref: bool) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).mode(0o666);
+
+    if no_deref {
+        options.custom_flags(O_NOFOLLOW);
+    }
+
+    options.open(path)
+}
+
+/// Prefix the message of `err` with `context`, keeping its kind, and `err` itself for
+/// [`os_error`].
+fn with_context(err: io::Error, context: String) -> io::Error {
+    io::Error::new(err.kind(), ContextError { context, err })
+}
+
+/// Get the code of the system error `err`, or of the one it adds context to.
+fn os_error(err: &io::Error) -> Option<i32> {
+    match err.get_ref().and_then(|inner| inner.downcast_ref::<ContextError>()) {
+        Some(inner) => inner.err.raw_os_error(),
+        None => err.raw_os_error(),
+    }
+}
+
+/// An error of touching a file, with the context it happened in.
+#[derive(Debug)]
+struct ContextError {
+    context: String,
+    err: io::Error,
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.context, self.err)
+    }
+}
+
+impl error::Error for ContextError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.err)
+    }
+}
+
+/// Get the metadata of `path`, without following symbolic links if `no_deref` is set.
+fn file_metadata(path: &str, no_deref: bool) -> io::Result<Metadata> {
+    if no_deref { fs::symlink_metadata(path) } else { fs::metadata(path) }
+}
+
+/// Setter of the times of symbolic links themselves, used with `-h`.
+///
+/// The tests use it to act as a platform where that is not supported.
+trait SymlinkTimes: fmt::Debug + Sync {
+    /// Set the times of the symbolic link `path`, leaving the ones that are `None` as they are.
+    fn set(
+        &self, path: &str, atime: Option<FileTime>, mtime: Option<FileTime>,
+    ) -> io::Result<()>;
+}
+
+/// Sets the times of symbolic links with the system calls that don't follow them.
+#[derive(Debug)]
+struct NoFollow;
+
+impl SymlinkTimes for NoFollow {
+    fn set(
+        &self, path: &str, atime: Option<FileTime>, mtime: Option<FileTime>,
+    ) -> io::Result<()> {
+        match (atime, mtime) {
+            (Some(atime), Some(mtime)) => set_symlink_file_times(path, atime, mtime),
+            _ => set_symlink_times(path, atime, mtime),
         }
     }
 }
@@ -63,87 +309,105 @@ struct TouchFlags<'a> {
     reference_path: Option<&'a str>,
     date: Option<&'a str>,
     timestamp: Option<&'a str>,
+    jobs: usize,
+    verbose: bool,
+    symlink_times: &'static dyn SymlinkTimes,
 }
 
 impl<'a> TouchFlags<'a> {
-    fn from_matches(matches: &'a ArgMatches<'a>) -> Self {
-        let time_val = matches.value_of("time").unwrap_or("");
-        let mut access_time = matches.is_present("accesstime")
-            || time_val == "access"
-            || time_val == "atime"
-            || time_val == "use";
-
-        let mut mod_time =
-            matches.is_present("modification") || time_val == "modify" || time_val == "mtime";
+    /// Get the flags from the `matches` of the command line.
+    ///
+    /// Fails if the WORD of `--time` is not one of the known ones, or if the number of `--jobs`
+    /// is not a positive number.
+    fn from_matches(matches: &'a ArgMatches<'a>) -> Result<Self, String> {
+        let (time_access, time_mod) = match matches.value_of("time") {
+            Some(word) => parse_time_word(word)?,
+            None => (false, false),
+        };
+        let mut access_time = matches.is_present("accesstime") || time_access;
+        let mut mod_time = matches.is_present("modification") || time_mod;
 
         if !access_time && !mod_time {
             access_time = true;
             mod_time = true;
         }
 
-        TouchFlags {
+        Ok(TouchFlags {
             access_time,
             mod_time,
-            no_create: matches.is_present("nocreate") || matches.is_present("no_deref"),
+            no_create: matches.is_present("nocreate"),
             no_deref: matches.is_present("no_deref"),
             reference_path: matches.value_of("reference"),
             date: matches.value_of("date"),
             timestamp: matches.value_of("timestamp"),
+            jobs: match matches.value_of("jobs") {
+                Some(jobs) => match jobs.parse() {
+                    Ok(jobs) if jobs > 0 => jobs,
+                    _ => return Err(format!("invalid number of jobs: '{}'", jobs)),
+                },
+                None => 1,
+            },
+            verbose: matches.is_present("verbose"),
+            symlink_times: &NoFollow,
+        })
+    }
+
+    /// Copy of the flags without the sources of the new times, the only ones borrowed from the
+    /// command line, for the threads that touch the files once the times are known.
+    fn without_time_sources(&self) -> TouchFlags<'static> {
+        TouchFlags {
+            access_time: self.access_time,
+            mod_time: self.mod_time,
+            no_create: self.no_create,
+            no_deref: self.no_deref,
+            reference_path: None,
+            date: None,
+            timestamp: None,
+            jobs: self.jobs,
+            verbose: self.verbose,
+            symlink_times: self.symlink_times,
         }
     }
 }
 
+/// Parse the WORD of `--time`, returning whether it selects the access time and the
+/// modification time.
+fn parse_time_word(word: &str) -> Result<(bool, bool), String> {
+    match word {
+        "access" | "atime" | "use" => Ok((true, false)),
+        "modify" | "mtime" => Ok((false, true)),
+        _ => Err(format!(
+            "invalid argument '{}' for '--time'\nValid arguments are:\n  - 'atime', 'access', \
+             'use'\n  - 'mtime', 'modify'\nTry 'touch --help' for more information.",
+            word
+        )),
+    }
+}
+
 /// Returns the correct `(atime, mtime)` acording to the `flags`.
 fn new_filetimes(flags: TouchFlags) -> Result<(FileTime, FileTime), String> {
     if let Some(flags_date) = flags.date {
-        let date = match PrimitiveDateTime::parse(&flags_date, "%Y-%m-%d %H:%M:%S") {
-            Ok(dt) => dt.assume_utc(),
-            Err(err) => return Err(format!("Problem parsing date arguments: {}", err)),
-        };
-        let time = FileTime::from_unix_time(date.timestamp(), date.microsecond());
+        if let Some(offset) = parse_relative_date(flags_date)? {
+            // Relative dates are an offset from the reference file times, if given, or from
+            // the current time.
+            let (atime, mtime) = match flags.reference_path {
+                Some(flags_reference) => reference_filetimes(flags_reference, flags.no_deref)?,
+                None => {
+                    let now = FileTime::from_system_time(SystemTime::now());
+                    (now, now)
+                },
+            };
+
+            return Ok((shift_filetime(atime, offset), shift_filetime(mtime, offset)));
+        }
+
+        let time = parse_date(flags_date)?;
 
         Ok((time, time))
     } else if let Some(flags_reference) = flags.reference_path {
-        let file_meta = match fs::metadata(flags_reference) {
-            Ok(m) => m,
-            Err(err) => {
-                return Err(format!(
-                    "Failed to get {} (OTHER_FILE) metadata: {}",
-                    flags_reference, err
-                ));
-            },
-        };
-
-        Ok((
-            FileTime::from_last_access_time(&file_meta),
-            FileTime::from_last_modification_time(&file_meta),
-        ))
+        reference_filetimes(flags_reference, flags.no_deref)
     } else if let Some(flags_timestamp) = flags.timestamp {
-        // PrimitiveDateTime::parse doesn't handle missing %C and %C%y in format,
-        // thus, based on the length of the input, the input string will be
-        // completed up to the "%C%y%m%d%H%M.%S" format
-        let current_date: PrimitiveDateTime = SystemTime::now().into();
-        let input = flags_timestamp.trim_start();
-        let input = match input.len() {
-            // CCYYMMDDhhmm.ss format, nothing to add
-            15 => input.to_owned(),
-            // YYMMDDhhmm.ss format, add century
-            13 => format!("{}{}", current_date.format("%C"), input),
-            // CCYYMMDDhhmm format, add .00 for seconds
-            12 => format!("{}.00", input),
-            // MMDDhhmm.ss format, add year
-            11 => format!("{}{}", current_date.format("%Y"), input),
-            // YYMMDDhhmm format, add century and .00 for seconds
-            10 => format!("{}{}.00", current_date.format("%C"), input),
-            // YYMMDDhhmm format, add year and .00 for seconds
-            8 => format!("{}{}.00", current_date.format("%Y"), input),
-            _ => return Err(format!("Unhandled timestamp format '{}'", input)),
-        };
-        let date = match PrimitiveDateTime::parse(&input, "%Y%m%d%H%M.%S") {
-            Ok(dt) => dt.assume_utc(),
-            Err(err) => return Err(format!("Problem parsing timestamp argument: {}", err)),
-        };
-        let time = FileTime::from_unix_time(date.timestamp(), date.microsecond());
+        let time = to_filetime(assume_local(parse_timestamp(flags_timestamp)?));
 
         Ok((time, time))
     } else {
@@ -153,14 +417,237 @@ fn new_filetimes(flags: TouchFlags) -> Result<(FileTime, FileTime), String> {
     }
 }
 
+/// Returns the `(atime, mtime)` of the `reference` file.
+fn reference_filetimes(reference: &str, no_deref: bool) -> Result<(FileTime, FileTime), String> {
+    let file_meta = match file_metadata(reference, no_deref) {
+        Ok(m) => m,
+        Err(err) => {
+            return Err(format!("Failed to get {} (OTHER_FILE) metadata: {}", reference, err));
+        },
+    };
+
+    Ok((
+        FileTime::from_last_access_time(&file_meta),
+        FileTime::from_last_modification_time(&file_meta),
+    ))
+}
+
+/// Parse a relative `--date` value into the offset it represents.
+///
+/// Relative dates are one or more `[+|-]NUMBER UNIT` items separated by spaces, like
+/// `-1 hour` or `+1 day 30 minutes`, where UNIT is one of `second`, `minute`, `hour`, `day`
+/// or `week`, in singular or plural form, and `sec` and `min` are accepted as abbreviations.
+/// Each item sign only applies to that item number.
+///
+/// Returns `None` if `date` is not a relative date.
+fn parse_relative_date(date: &str) -> Result<Option<Duration>, String> {
+    let items: Vec<_> = date.split_whitespace().collect();
+
+    if items.is_empty() || items.len() % 2 != 0 {
+        return Ok(None);
+    }
+
+    let mut offset = Duration::zero();
+    for item in items.chunks(2) {
+        let seconds_per_unit = match item[1] {
+            "sec" | "secs" | "second" | "seconds" => 1,
+            "min" | "mins" | "minute" | "minutes" => 60,
+            "hour" | "hours" => 60 * 60,
+            "day" | "days" => 24 * 60 * 60,
+            "week" | "weeks" => 7 * 24 * 60 * 60,
+            _ => return Ok(None),
+        };
+
+        let number: i64 = match item[0].parse() {
+            Ok(n) => n,
+            Err(_) => return Ok(None),
+        };
+
+        let seconds = number
+            .checked_mul(seconds_per_unit)
+            .ok_or_else(|| format!("Problem parsing date arguments: '{}' is too large", date))?;
+        offset += Duration::seconds(seconds);
+    }
+
+    Ok(Some(offset))
+}
+
+/// Shift `time` by `offset`.
+fn shift_filetime(time: FileTime, offset: Duration) -> FileTime {
+    const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+    let nanos = i128::from(time.unix_seconds()) * NANOS_PER_SEC
+        + i128::from(time.nanoseconds())
+        + offset.whole_nanoseconds();
+
+    FileTime::from_unix_time(
+        nanos.div_euclid(NANOS_PER_SEC) as i64,
+        nanos.rem_euclid(NANOS_PER_SEC) as u32,
+    )
+}
+
+/// Accepted `--date` formats for values without an explicit offset, in the order they are
+/// tried.
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"];
+
+/// Parse a `--date` value.
+///
+/// The accepted values are, in the order they are tried:
+///  - `@SECONDS[.frac]`, a number of seconds since the Unix epoch;
+///  - RFC 3339 date-times with an offset, like `2021-01-01T13:00:00+02:00`, where the `T`
+///    can also be a space;
+///  - `Y-M-D h:m:s[.frac]` and `Y-M-DTh:m:s[.frac]` date-times, in the local timezone;
+///  - `Y-M-D` dates, meaning midnight of that day in the local timezone.
+///
+/// The optional fraction of seconds can have up to 9 digits, giving nanosecond precision.
+fn parse_date(date: &str) -> Result<FileTime, String> {
+    if let Some(seconds) = date.strip_prefix('@') {
+        return parse_epoch(seconds);
+    }
+
+    // Values with an explicit offset already are an absolute time, so they must not be
+    // adjusted to the local timezone
+    let offset_date = OffsetDateTime::parse(date, Format::Rfc3339)
+        .or_else(|_| OffsetDateTime::parse(date.replacen(' ', "T", 1), Format::Rfc3339));
+    if let Ok(date) = offset_date {
+        return Ok(to_filetime(date));
+    }
+
+    Ok(to_filetime(assume_local(parse_naive_date(date)?)))
+}
+
+/// Parse a `--date` value that has no offset information, trying each of [`DATE_FORMATS`]
+/// and then a date-only value.
+fn parse_naive_date(date: &str) -> Result<PrimitiveDateTime, String> {
+    let (datetime, fraction) = match date.find('.') {
+        Some(pos) => (&date[..pos], Some(&date[pos + 1..])),
+        None => (date, None),
+    };
+
+    let nanos = match fraction {
+        Some(f) if f.is_empty() || f.len() > 9 || !f.bytes().all(|b| b.is_ascii_digit()) => {
+            return Err("Problem parsing date arguments: invalid fraction of seconds".to_owned());
+        },
+        // Ok to unwrap, the fraction has between 1 and 9 digits
+        Some(f) => format!("{:0<9}", f).parse().unwrap(),
+        None => 0,
+    };
+
+    for format in DATE_FORMATS {
+        if let Ok(dt) = PrimitiveDateTime::parse(datetime, format) {
+            return Ok(dt + Duration::nanoseconds(nanos));
+        }
+    }
+
+    match Date::parse(datetime, "%Y-%m-%d") {
+        Ok(day) if fraction.is_none() => Ok(day.midnight()),
+        _ => Err(format!("Problem parsing date arguments: invalid date format '{}'", date)),
+    }
+}
+
+/// Parse a `[-]SECONDS[.frac]` number of seconds since the Unix epoch.
+fn parse_epoch(seconds: &str) -> Result<FileTime, String> {
+    let invalid = || format!("Problem parsing date arguments: invalid epoch '@{}'", seconds);
+
+    let (negative, unsigned) = match seconds.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, seconds.strip_prefix('+').unwrap_or(seconds)),
+    };
+    let (whole, fraction) = match unsigned.find('.') {
+        Some(pos) => (&unsigned[..pos], &unsigned[pos + 1..]),
+        None => (unsigned, ""),
+    };
+
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if whole.is_empty() || !is_digits(whole) || !is_digits(fraction) || fraction.len() > 9 {
+        return Err(invalid());
+    }
+
+    let whole: i64 = whole.parse().map_err(|_| invalid())?;
+    // Ok to unwrap, the fraction has at most 9 digits
+    let nanos: i64 = format!("{:0<9}", fraction).parse().unwrap();
+
+    if negative {
+        // Seconds are rounded down, so the nanoseconds are counted forward from there
+        let nanos_borrow = if nanos > 0 { 1 } else { 0 };
+        let secs = whole.checked_add(nanos_borrow).ok_or_else(invalid)?;
+        Ok(FileTime::from_unix_time(-secs, ((1_000_000_000 - nanos) % 1_000_000_000) as u32))
+    } else {
+        Ok(FileTime::from_unix_time(whole, nanos as u32))
+    }
+}
+
+/// Interpret a date-time without offset information as a time in the local timezone.
+///
+/// The local timezone is the one set by the `TZ` environment variable, or the system one if
+/// it is not set. If the local offset cannot be determined, UTC is used.
+fn assume_local(date: PrimitiveDateTime) -> OffsetDateTime {
+    // The local offset depends on the instant (i.e. daylight saving time), so we first get it
+    // as if `date` was in UTC, and then get it again at the instant found with that offset.
+    let offset = UtcOffset::local_offset_at(date.assume_utc());
+    let offset = UtcOffset::local_offset_at(date.assume_offset(offset));
+
+    date.assume_offset(offset)
+}
+
+fn to_filetime(date: OffsetDateTime) -> FileTime {
+    FileTime::from_unix_time(date.timestamp(), date.nanosecond())
+}
+
+/// Parse a POSIX `[[CC]YY]MMDDhhmm[.ss]` timestamp.
+///
+/// When the century is omitted, years 69 to 99 are considered to be in the 20th century
+/// and years 00 to 68 in the 21st, as specified by POSIX. When the year is omitted, the
+/// current year is used.
+fn parse_timestamp(stamp: &str) -> Result<PrimitiveDateTime, String> {
+    let invalid = || format!("invalid date format '{}'", stamp);
+
+    let input = stamp.trim_start();
+    let (datetime, seconds) = match input.find('.') {
+        Some(pos) => (&input[..pos], &input[pos + 1..]),
+        None => (input, "00"),
+    };
+
+    if seconds.len() != 2 || !seconds.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    if !datetime.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let input = match datetime.len() {
+        // CCYYMMDDhhmm format, nothing to add
+        12 => format!("{}{}", datetime, seconds),
+        // YYMMDDhhmm format, add century
+        10 => {
+            // Ok to unwrap, we checked that all characters are digits
+            let year: u8 = datetime[..2].parse().unwrap();
+            let century = if year >= 69 { 19 } else { 20 };
+            format!("{}{}{}", century, datetime, seconds)
+        },
+        // MMDDhhmm format, add current year
+        8 => {
+            let current_date: PrimitiveDateTime = SystemTime::now().into();
+            format!("{}{}{}", current_date.year(), datetime, seconds)
+        },
+        _ => return Err(invalid()),
+    };
+
+    PrimitiveDateTime::parse(&input, "%Y%m%d%H%M%S").map_err(|_| invalid())
+}
+
 /// Update the times of the `path` acording with the `flags`.
 fn update_time(
-    path: &str, new_atime: FileTime, new_mtime: FileTime, meta: &Metadata, flags: TouchFlags,
-) {
+    path: &str, new_atime: FileTime, new_mtime: FileTime, flags: TouchFlags,
+) -> io::Result<()> {
     match (flags.access_time, flags.mod_time) {
-        (true, false) => update_access_time(&path, new_atime, meta, flags.no_deref),
-        (false, true) => update_modification_time(&path, new_mtime, meta, flags.no_deref),
-        (true, true) => update_both_time(&path, new_atime, new_mtime, flags.no_deref),
+        (true, false) => update_access_time(path, new_atime, flags)
+            .map_err(|err| with_context(err, format!("Failed to update {} access time", path))),
+        (false, true) => update_modification_time(path, new_mtime, flags).map_err(
+            |err| with_context(err, format!("Failed to update {} modification time", path)),
+        ),
+        (true, true) => update_both_time(path, new_atime, new_mtime, flags)
+            .map_err(|err| with_context(err, format!("Failed to update {} time", path))),
 
         // Unreachable because when creating `TouchFlags` if both are false, we change both to true
         // since de default behaviour is to change both. So (false, false) will never happen, and if
@@ -169,36 +656,52 @@ fn update_time(
     }
 }
 
-fn update_access_time(path: &str, new_atime: FileTime, meta: &Metadata, no_deref: bool) {
-    if no_deref {
-        let mtime = FileTime::from_last_modification_time(meta);
+fn update_access_time(path: &str, new_atime: FileTime, flags: TouchFlags) -> io::Result<()> {
+    if flags.no_deref {
+        flags.symlink_times.set(path, Some(new_atime), None)
+    } else {
+        set_file_atime(path, new_atime)
+    }
+}
 
-        if let Err(err) = set_symlink_file_times(&path, new_atime, mtime) {
-            eprintln!("touch: Failed to update {} access time: {}", &path, err);
-        }
-    } else if let Err(err) = set_file_atime(&path, new_atime) {
-        eprintln!("touch: Failed to update {} access time: {}", &path, err);
+fn update_modification_time(path: &str, new_mtime: FileTime, flags: TouchFlags) -> io::Result<()> {
+    if flags.no_deref {
+        flags.symlink_times.set(path, None, Some(new_mtime))
+    } else {
+        set_file_mtime(path, new_mtime)
     }
 }
 
-fn update_modification_time(path: &str, new_mtime: FileTime, meta: &Metadata, no_deref: bool) {
-    if no_deref {
-        let atime = FileTime::from_last_access_time(meta);
+/// Set the times of `path` without following a symbolic link, leaving the ones that are `None`
+/// as they are.
+///
+/// The time left as is is not read and set back, so a change made to it while touching the
+/// file is not undone.
+fn set_symlink_times(
+    path: &str, atime: Option<FileTime>, mtime: Option<FileTime>,
+) -> io::Result<()> {
+    let timespec_of = |time: Option<FileTime>| match time {
+        Some(time) => {
+            timespec { tv_sec: time.unix_seconds() as _, tv_nsec: time.nanoseconds() as _ }
+        },
+        None => timespec { tv_sec: 0, tv_nsec: UTIME_OMIT as _ },
+    };
+    let times = [timespec_of(atime), timespec_of(mtime)];
+    let path = CString::new(path)?;
 
-        if let Err(err) = set_symlink_file_times(&path, atime, new_mtime) {
-            eprintln!("touch: Failed to update {} modification time: {}", &path, err);
-        }
-    } else if let Err(err) = set_file_mtime(&path, new_mtime) {
-        eprintln!("touch: Failed to update {} modification time: {}", &path, err);
+    if unsafe { utimensat(AT_FDCWD, path.as_ptr(), times.as_ptr(), AT_SYMLINK_NOFOLLOW) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
     }
 }
 
-fn update_both_time(path: &str, new_atime: FileTime, new_mtime: FileTime, no_deref: bool) {
-    if no_deref {
-        if let Err(err) = set_symlink_file_times(&path, new_atime, new_mtime) {
-            eprintln!("touch: Failed to update {} time: {}", &path, err);
-        }
-    } else if let Err(err) = set_file_times(&path, new_atime, new_mtime) {
-        eprintln!("touch: Failed to update {} time: {}", &path, err);
+fn update_both_time(
+    path: &str, new_atime: FileTime, new_mtime: FileTime, flags: TouchFlags,
+) -> io::Result<()> {
+    if flags.no_deref {
+        flags.symlink_times.set(path, Some(new_atime), Some(new_mtime))
+    } else {
+        set_file_times(path, new_atime, new_mtime)
     }
 }
@@ -1,17 +1,29 @@
 use std::{
+    env,
     fs::{metadata, remove_file},
     io,
+    sync::MutexGuard,
 };
 
+use coreutils_core::testutil::{lock_shared_state, TempDir};
+
 use super::*;
 
+/// Set the `TZ` environment variable, that is shared by all tests running in parallel, holding
+/// the lock of the shared state while the returned guard is alive.
+fn set_tz(tz: &str) -> MutexGuard<'static, ()> {
+    let guard = lock_shared_state();
+    env::set_var("TZ", tz);
+    guard
+}
+
 #[test]
 fn touch_create_empty_files() {
     let matches = ArgMatches::new();
-    let flags = TouchFlags::from_matches(&matches);
+    let flags = TouchFlags::from_matches(&matches).unwrap();
     let files = vec!["file1.rs", "file2.rs"];
 
-    touch(&files, flags);
+    touch(&files, flags, &mut io::sink());
 
     assert_eq!(metadata("file1.rs").is_ok(), true);
     assert_eq!(metadata("file2.rs").is_ok(), true);
@@ -21,7 +33,7 @@ fn touch_create_empty_files() {
 #[test]
 fn touch_update_existing_files() {
     let matches = ArgMatches::new();
-    let flags = TouchFlags::from_matches(&matches);
+    let flags = TouchFlags::from_matches(&matches).unwrap();
     let files = vec!["file3.rs", "file4.rs"];
 
     File::create("file3.rs").unwrap();
@@ -30,7 +42,7 @@ fn touch_update_existing_files() {
     let file1_atime = FileTime::from_last_access_time(&file1_metadata);
 
     // update and create files
-    touch(&files, flags);
+    touch(&files, flags, &mut io::sink());
 
     let new_file1_metadata = metadata("file3.rs").unwrap();
     let new_file1_mtime = FileTime::from_last_modification_time(&new_file1_metadata);
@@ -48,7 +60,7 @@ fn touch_update_existing_files() {
 fn touch_update_only_access_time() {
     let matches = cli::create_app().get_matches_from(vec!["touch", "-a", "file5.rs", "file6.rs"]);
 
-    let flags = TouchFlags::from_matches(&matches);
+    let flags = TouchFlags::from_matches(&matches).unwrap();
 
     let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
 
@@ -58,7 +70,7 @@ fn touch_update_only_access_time() {
     let file1_atime = FileTime::from_last_access_time(&file1_metadata);
 
     // update and create files
-    touch(&files, flags);
+    touch(&files, flags, &mut io::sink());
 
     file1_metadata = metadata(&files[0]).unwrap();
     let new_file1_atime = FileTime::from_last_access_time(&file1_metadata);
@@ -72,7 +84,7 @@ fn touch_update_only_access_time() {
 fn touch_update_only_modification_time() {
     let matches = cli::create_app().get_matches_from(vec!["touch", "-m", "file7.rs", "file8.rs"]);
 
-    let flags = TouchFlags::from_matches(&matches);
+    let flags = TouchFlags::from_matches(&matches).unwrap();
 
     let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
 
@@ -82,7 +94,7 @@ fn touch_update_only_modification_time() {
     let file1_mtime = FileTime::from_last_modification_time(&file1_metadata);
 
     // update and create files
-    touch(&files, flags);
+    touch(&files, flags, &mut io::sink());
 
     file1_metadata = metadata(&files[0]).unwrap();
     let new_file1_mtime = FileTime::from_last_modification_time(&file1_metadata);
@@ -95,6 +107,7 @@ fn touch_update_only_modification_time() {
 
 #[test]
 fn touch_update_time_with_date() {
+    let _tz = set_tz("UTC");
     let matches = cli::create_app().get_matches_from(vec![
         "touch",
         "-d=2009-01-03 03:13:00",
@@ -102,14 +115,14 @@ fn touch_update_time_with_date() {
         "file10.rs",
     ]);
 
-    let flags = TouchFlags::from_matches(&matches);
+    let flags = TouchFlags::from_matches(&matches).unwrap();
 
     let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
 
     File::create(&files[0]).unwrap();
 
     // update and create files
-    touch(&files, flags);
+    touch(&files, flags, &mut io::sink());
 
     for curr_file in &files {
         let file1_metadata = metadata(curr_file).unwrap();
@@ -130,6 +143,7 @@ fn touch_update_time_with_date() {
 
 #[test]
 fn touch_update_time_with_timestamp() {
+    let _tz = set_tz("UTC");
     let matches = cli::create_app().get_matches_from(vec![
         "touch",
         "-t 200901030313.00",
@@ -137,14 +151,14 @@ fn touch_update_time_with_timestamp() {
         "file12.rs",
     ]);
 
-    let flags = TouchFlags::from_matches(&matches);
+    let flags = TouchFlags::from_matches(&matches).unwrap();
 
     let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
 
     File::create(&files[0]).unwrap();
 
     // update and create files
-    touch(&files, flags);
+    touch(&files, flags, &mut io::sink());
 
     for curr_file in &files {
         let file1_metadata = metadata(curr_file).unwrap();
@@ -169,3 +183,833 @@ fn remove_test_files(files: &[&str]) -> io::Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn touch_update_time_with_reference() {
+    let matches = cli::create_app().get_matches_from(vec![
+        "touch",
+        "-r",
+        "file13.rs",
+        "file14.rs",
+        "file15.rs",
+    ]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    File::create("file13.rs").unwrap();
+    let ref_atime = FileTime::from_unix_time(1_000_000_000, 0);
+    let ref_mtime = FileTime::from_unix_time(1_100_000_000, 0);
+    set_file_times("file13.rs", ref_atime, ref_mtime).unwrap();
+
+    File::create(files[0]).unwrap();
+
+    // update and create files
+    touch(&files, flags, &mut io::sink());
+
+    for curr_file in &files {
+        let file_metadata = metadata(curr_file).unwrap();
+
+        // check access and modification time are equal to the reference ones
+        assert_eq!(FileTime::from_last_access_time(&file_metadata), ref_atime);
+        assert_eq!(FileTime::from_last_modification_time(&file_metadata), ref_mtime);
+    }
+    remove_test_files(&files).unwrap();
+    remove_test_files(&["file13.rs"]).unwrap();
+}
+
+#[test]
+fn touch_time_words() {
+    for (word, expected) in &[
+        ("access", (true, false)),
+        ("atime", (true, false)),
+        ("use", (true, false)),
+        ("modify", (false, true)),
+        ("mtime", (false, true)),
+    ] {
+        let matches = cli::create_app().get_matches_from(vec!["touch", "--time", word, "file"]);
+        let flags = TouchFlags::from_matches(&matches).unwrap();
+
+        assert_eq!(*expected, (flags.access_time, flags.mod_time));
+    }
+}
+
+#[test]
+fn touch_invalid_time_word() {
+    let matches = cli::create_app().get_matches_from(vec!["touch", "--time=modfiy", "file"]);
+
+    let err = TouchFlags::from_matches(&matches).unwrap_err();
+
+    assert!(err.starts_with("invalid argument 'modfiy' for '--time'\nValid arguments are:\n"));
+    assert!(err.ends_with("\nTry 'touch --help' for more information."));
+}
+
+#[test]
+fn touch_update_only_modification_time_with_reference() {
+    let matches = cli::create_app().get_matches_from(vec![
+        "touch",
+        "--time=modify",
+        "-r",
+        "file16.rs",
+        "file17.rs",
+    ]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    File::create("file16.rs").unwrap();
+    let ref_time = FileTime::from_unix_time(1_000_000_000, 0);
+    set_file_times("file16.rs", ref_time, ref_time).unwrap();
+
+    File::create(files[0]).unwrap();
+    let old_atime = FileTime::from_unix_time(1_200_000_000, 0);
+    set_file_times(files[0], old_atime, old_atime).unwrap();
+
+    touch(&files, flags, &mut io::sink());
+
+    let file_metadata = metadata(files[0]).unwrap();
+
+    // check that only the modification time was changed
+    assert_eq!(FileTime::from_last_access_time(&file_metadata), old_atime);
+    assert_eq!(FileTime::from_last_modification_time(&file_metadata), ref_time);
+
+    remove_test_files(&files).unwrap();
+    remove_test_files(&["file16.rs"]).unwrap();
+}
+
+#[test]
+fn touch_parse_timestamp() {
+    let expected = |date| PrimitiveDateTime::parse(date, "%Y-%m-%d %H:%M:%S").unwrap();
+
+    assert_eq!(parse_timestamp("202402291330.45").unwrap(), expected("2024-02-29 13:30:45"));
+    assert_eq!(parse_timestamp("202402291330").unwrap(), expected("2024-02-29 13:30:00"));
+    assert_eq!(parse_timestamp("6901030313.00").unwrap(), expected("1969-01-03 03:13:00"));
+    assert_eq!(parse_timestamp("6801030313").unwrap(), expected("2068-01-03 03:13:00"));
+    assert_eq!(parse_timestamp("0001030313.59").unwrap(), expected("2000-01-03 03:13:59"));
+
+    let current_year = PrimitiveDateTime::from(SystemTime::now()).year();
+    assert_eq!(parse_timestamp("01030313").unwrap().year(), current_year);
+}
+
+#[test]
+fn touch_parse_invalid_timestamp() {
+    assert!(parse_timestamp("").is_err());
+    assert!(parse_timestamp("2009").is_err());
+    assert!(parse_timestamp("200901030313.0").is_err());
+    assert!(parse_timestamp("200901030313.000").is_err());
+    assert!(parse_timestamp("2009010303a3.00").is_err());
+    assert!(parse_timestamp("200913030313.00").is_err());
+    assert_eq!(parse_timestamp("foo").unwrap_err(), "invalid date format 'foo'");
+}
+
+#[test]
+fn touch_update_time_with_subsecond_date() {
+    let _tz = set_tz("UTC");
+    let matches = cli::create_app().get_matches_from(vec![
+        "touch",
+        "-d",
+        "2021-01-01 00:00:00.123456789",
+        "file18.rs",
+    ]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    File::create(files[0]).unwrap();
+
+    touch(&files, flags, &mut io::sink());
+
+    let file_metadata = metadata(files[0]).unwrap();
+    let file_mtime = FileTime::from_last_modification_time(&file_metadata);
+
+    // check that the fraction of seconds was kept as nanoseconds
+    assert_eq!(file_mtime.unix_seconds(), 1_609_459_200);
+    assert_eq!(file_mtime.nanoseconds(), 123_456_789);
+
+    remove_test_files(&files).unwrap();
+}
+
+#[test]
+fn touch_parse_date_fraction() {
+    assert_eq!(parse_date("2021-01-01 00:00:00.5").unwrap().nanoseconds(), 500_000_000);
+    assert_eq!(parse_date("2021-01-01 00:00:00.000001").unwrap().nanoseconds(), 1_000);
+    assert!(parse_date("2021-01-01 00:00:00.").is_err());
+    assert!(parse_date("2021-01-01 00:00:00.1234567890").is_err());
+}
+
+#[test]
+fn touch_no_create_missing_file_with_date() {
+    let matches = cli::create_app().get_matches_from(vec![
+        "touch",
+        "-c",
+        "-d",
+        "2009-01-03 03:13:00",
+        "file19.rs",
+    ]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    touch(&files, flags, &mut io::sink());
+
+    assert!(metadata(files[0]).is_err());
+}
+
+#[test]
+fn touch_no_create_missing_file() {
+    let matches = cli::create_app().get_matches_from(vec!["touch", "-c", "file20.rs"]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    touch(&files, flags, &mut io::sink());
+
+    assert!(metadata(files[0]).is_err());
+}
+
+#[test]
+fn touch_create_file_with_date() {
+    let _tz = set_tz("UTC");
+    let matches = cli::create_app().get_matches_from(vec![
+        "touch",
+        "-d",
+        "2000-01-01 00:00:00",
+        "file21.rs",
+    ]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    // file does not exist before touching it
+    assert!(metadata(files[0]).is_err());
+
+    touch(&files, flags, &mut io::sink());
+
+    let file_metadata = metadata(files[0]).unwrap();
+    let expected = FileTime::from_unix_time(946_684_800, 0);
+
+    // check that the created file got the requested times
+    assert_eq!(FileTime::from_last_modification_time(&file_metadata), expected);
+    assert_eq!(FileTime::from_last_access_time(&file_metadata), expected);
+
+    remove_test_files(&files).unwrap();
+}
+
+#[test]
+fn touch_no_deref_creates_missing_file() {
+    let matches = cli::create_app().get_matches_from(vec!["touch", "-h", "file22.rs"]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    touch(&files, flags, &mut io::sink());
+
+    assert!(metadata(files[0]).is_ok());
+
+    remove_test_files(&files).unwrap();
+}
+
+#[test]
+fn touch_exit_code_on_failure() {
+    let matches = cli::create_app().get_matches_from(vec![
+        "touch",
+        "file23.rs",
+        "missing_dir/file24.rs",
+        "file25.rs",
+    ]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    assert_eq!(touch(&files, flags, &mut io::sink()), 1);
+
+    // check that the remaining files were still processed
+    assert!(metadata("file23.rs").is_ok());
+    assert!(metadata("file25.rs").is_ok());
+
+    remove_test_files(&["file23.rs", "file25.rs"]).unwrap();
+}
+
+#[test]
+fn touch_exit_code_on_success() {
+    let matches = cli::create_app().get_matches_from(vec!["touch", "file26.rs", "file27.rs"]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    assert_eq!(touch(&files, flags, &mut io::sink()), 0);
+
+    remove_test_files(&files).unwrap();
+}
+
+#[test]
+fn touch_parse_date_epoch() {
+    assert_eq!(parse_date("@0").unwrap(), FileTime::from_unix_time(0, 0));
+    assert_eq!(parse_date("@1700000000").unwrap(), FileTime::from_unix_time(1_700_000_000, 0));
+    assert_eq!(parse_date("@-5").unwrap(), FileTime::from_unix_time(-5, 0));
+    assert_eq!(parse_date("@1.25").unwrap(), FileTime::from_unix_time(1, 250_000_000));
+    assert_eq!(parse_date("@-5.25").unwrap(), FileTime::from_unix_time(-6, 750_000_000));
+
+    assert!(parse_date("@").is_err());
+    assert!(parse_date("@-").is_err());
+    assert!(parse_date("@abc").is_err());
+    assert!(parse_date("@12a").is_err());
+    assert!(parse_date("@1.").is_ok());
+}
+
+#[test]
+fn touch_parse_date_formats() {
+    let _tz = set_tz("UTC");
+    let expected = FileTime::from_unix_time(1_609_506_000, 0);
+
+    assert_eq!(parse_date("2021-01-01 13:00:00").unwrap(), expected);
+    assert_eq!(parse_date("2021-01-01T13:00:00").unwrap(), expected);
+    assert_eq!(parse_date("2021-01-01T15:00:00+02:00").unwrap(), expected);
+    assert_eq!(parse_date("2021-01-01T13:00:00Z").unwrap(), expected);
+    assert_eq!(
+        parse_date("2021-01-01T13:00:00.5").unwrap(),
+        FileTime::from_unix_time(1_609_506_000, 500_000_000)
+    );
+    assert_eq!(parse_date("2021-01-01").unwrap(), FileTime::from_unix_time(1_609_459_200, 0));
+
+    assert!(parse_date("2021-01-01.5").is_err());
+    assert!(parse_date("not a date").is_err());
+}
+
+#[test]
+fn touch_parse_date_local_timezone() {
+    // UTC+2
+    let _tz = set_tz("AAA-2");
+
+    assert_eq!(
+        parse_date("2021-06-01 12:00:00").unwrap(),
+        FileTime::from_unix_time(1_622_541_600, 0)
+    );
+    assert_eq!(parse_date("2021-06-01").unwrap(), FileTime::from_unix_time(1_622_498_400, 0));
+    assert_eq!(
+        to_filetime(assume_local(parse_timestamp("202106011200").unwrap())),
+        FileTime::from_unix_time(1_622_541_600, 0)
+    );
+
+    // UTC-5
+    env::set_var("TZ", "BBB+5");
+
+    assert_eq!(
+        parse_date("2021-06-01 12:00:00").unwrap(),
+        FileTime::from_unix_time(1_622_566_800, 0)
+    );
+    assert_eq!(parse_date("2021-06-01").unwrap(), FileTime::from_unix_time(1_622_523_600, 0));
+
+    // explicit offsets must not be adjusted to the local timezone
+    let expected = FileTime::from_unix_time(1_622_541_600, 0);
+    assert_eq!(parse_date("2021-06-01 12:00:00+02:00").unwrap(), expected);
+    assert_eq!(parse_date("2021-06-01T12:00:00+02:00").unwrap(), expected);
+    assert_eq!(parse_date("2021-06-01T10:00:00Z").unwrap(), expected);
+}
+
+#[test]
+fn touch_one_missing_parent() {
+    let matches = ArgMatches::new();
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+    let time = FileTime::from_unix_time(0, 0);
+
+    let err = touch_one("missing_dir/file28.rs", time, time, flags).unwrap_err();
+
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(err.to_string().starts_with("Failed to create file missing_dir/file28.rs: "));
+}
+
+#[test]
+fn touch_one_permission_denied() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    use coreutils_core::libc::geteuid;
+
+    // Privileged users can write anyway, so the check only makes sense for everyone else
+    if unsafe { geteuid() } == 0 {
+        return;
+    }
+
+    let matches = ArgMatches::new();
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+    let time = FileTime::from_unix_time(0, 0);
+
+    let dir = TempDir::new("touch-read-only").unwrap();
+    let file = dir.join("file29.rs");
+    let file = file.to_str().unwrap();
+    fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o555)).unwrap();
+
+    let err = touch_one(file, time, time, flags).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    assert!(err.to_string().starts_with(&format!("Failed to create file {}: ", file)));
+
+    fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn touch_one_no_create_missing_file() {
+    let matches = cli::create_app().get_matches_from(vec!["touch", "-c", "file30.rs"]);
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+    let time = FileTime::from_unix_time(0, 0);
+
+    assert_eq!(Touched::Skipped, touch_one("file30.rs", time, time, flags).unwrap());
+    assert!(metadata("file30.rs").is_err());
+}
+
+#[test]
+fn touch_update_time_with_reference_and_relative_date() {
+    let ref_time = FileTime::from_unix_time(1_000_000_000, 500);
+
+    File::create("file31.rs").unwrap();
+    set_file_times("file31.rs", ref_time, ref_time).unwrap();
+
+    for (date, expected) in &[
+        ("+30 minutes", FileTime::from_unix_time(1_000_001_800, 500)),
+        ("-2 days", FileTime::from_unix_time(999_827_200, 500)),
+        ("-1 hour +30 min", FileTime::from_unix_time(999_998_200, 500)),
+    ] {
+        let matches = cli::create_app().get_matches_from(vec![
+            "touch",
+            "-r",
+            "file31.rs",
+            "-d",
+            date,
+            "file32.rs",
+        ]);
+
+        let flags = TouchFlags::from_matches(&matches).unwrap();
+
+        let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+        touch(&files, flags, &mut io::sink());
+
+        let file_metadata = metadata(files[0]).unwrap();
+
+        assert_eq!(FileTime::from_last_modification_time(&file_metadata), *expected);
+        assert_eq!(FileTime::from_last_access_time(&file_metadata), *expected);
+    }
+
+    remove_test_files(&["file31.rs", "file32.rs"]).unwrap();
+}
+
+#[test]
+fn touch_parse_relative_date() {
+    assert_eq!(parse_relative_date("1 sec").unwrap(), Some(Duration::seconds(1)));
+    assert_eq!(parse_relative_date("-2 weeks").unwrap(), Some(Duration::weeks(-2)));
+    assert_eq!(parse_relative_date("+1 day -1 hour").unwrap(), Some(Duration::hours(23)));
+
+    assert_eq!(parse_relative_date("2021-01-01 00:00:00").unwrap(), None);
+    assert_eq!(parse_relative_date("@5").unwrap(), None);
+    assert_eq!(parse_relative_date("1 hour 2").unwrap(), None);
+    assert_eq!(parse_relative_date("1 month").unwrap(), None);
+    assert!(parse_relative_date("9223372036854775807 weeks").is_err());
+}
+
+#[test]
+fn touch_shift_filetime() {
+    let time = FileTime::from_unix_time(10, 250_000_000);
+
+    assert_eq!(
+        shift_filetime(time, Duration::milliseconds(-500)),
+        FileTime::from_unix_time(9, 750_000_000)
+    );
+    assert_eq!(
+        shift_filetime(time, Duration::seconds(-20)),
+        FileTime::from_unix_time(-10, 250_000_000)
+    );
+}
+
+#[test]
+fn touch_no_deref_dangling_symlink() {
+    use std::os::unix::fs::symlink;
+
+    let matches = cli::create_app().get_matches_from(vec![
+        "touch",
+        "-h",
+        "-d",
+        "@1000000000",
+        "file33.rs",
+    ]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    symlink("file34.rs", "file33.rs").unwrap();
+
+    touch(&files, flags, &mut io::sink());
+
+    // check that the symlink target was not created
+    assert!(metadata("file34.rs").is_err());
+
+    // check that the symlink itself had its times changed
+    let link_metadata = fs::symlink_metadata("file33.rs").unwrap();
+    assert_eq!(
+        FileTime::from_last_modification_time(&link_metadata),
+        FileTime::from_unix_time(1_000_000_000, 0)
+    );
+
+    remove_test_files(&files).unwrap();
+}
+
+#[test]
+fn touch_no_deref_symlink_reference() {
+    use std::os::unix::fs::symlink;
+
+    let matches = cli::create_app().get_matches_from(vec![
+        "touch",
+        "-h",
+        "-r",
+        "file57.rs",
+        "file59.rs",
+    ]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    File::create("file58.rs").unwrap();
+    let target_time = FileTime::from_unix_time(1_500_000_000, 0);
+    set_file_times("file58.rs", target_time, target_time).unwrap();
+
+    symlink("file58.rs", "file57.rs").unwrap();
+    let link_atime = FileTime::from_unix_time(1_000_000_000, 0);
+    let link_mtime = FileTime::from_unix_time(1_100_000_000, 0);
+    set_symlink_file_times("file57.rs", link_atime, link_mtime).unwrap();
+
+    File::create("file59.rs").unwrap();
+
+    touch(&files, flags, &mut io::sink());
+
+    // check that the times come from the symlink itself, not from its target
+    let file_metadata = metadata("file59.rs").unwrap();
+    assert_eq!(FileTime::from_last_access_time(&file_metadata), link_atime);
+    assert_eq!(FileTime::from_last_modification_time(&file_metadata), link_mtime);
+
+    remove_test_files(&["file57.rs", "file58.rs", "file59.rs"]).unwrap();
+}
+
+#[test]
+fn touch_no_deref_access_time_keeps_concurrent_mtime() {
+    let matches = cli::create_app().get_matches_from(vec![
+        "touch",
+        "-h",
+        "-a",
+        "-d",
+        "@1500000000",
+        "file61.rs",
+    ]);
+
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    File::create("file61.rs").unwrap();
+    let last_mtime = FileTime::from_unix_time(1_000_000_200, 0);
+
+    // The modification time is changed while the access time is touched, and the last change
+    // must stay, as touch never sets the modification time back
+    let changer = thread::spawn(|| {
+        for secs in 1_000_000_001..=1_000_000_200 {
+            set_file_mtime("file61.rs", FileTime::from_unix_time(secs, 0)).unwrap();
+        }
+    });
+    for _ in 0..200 {
+        let flags = TouchFlags::from_matches(&matches).unwrap();
+        assert_eq!(touch(&files, flags, &mut io::sink()), 0);
+    }
+    changer.join().unwrap();
+
+    let file_metadata = metadata("file61.rs").unwrap();
+    assert_eq!(FileTime::from_last_modification_time(&file_metadata), last_mtime);
+    assert_eq!(
+        FileTime::from_last_access_time(&file_metadata),
+        FileTime::from_unix_time(1_500_000_000, 0)
+    );
+
+    remove_test_files(&files).unwrap();
+}
+
+/// Fails like a platform that can't set the times of symbolic links.
+#[derive(Debug)]
+struct Unsupported;
+
+impl SymlinkTimes for Unsupported {
+    fn set(&self, _: &str, _: Option<FileTime>, _: Option<FileTime>) -> io::Result<()> {
+        Err(io::Error::from_raw_os_error(ENOTSUP))
+    }
+}
+
+#[test]
+fn touch_no_deref_unsupported_reported_once() {
+    let matches = cli::create_app().get_matches_from(vec!["touch", "-h", "file62.rs", "file63.rs"]);
+    let mut flags = TouchFlags::from_matches(&matches).unwrap();
+    flags.symlink_times = &Unsupported;
+
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+    assert_eq!(1, touch(&files, flags, &mut io::sink()));
+
+    let time = FileTime::from_unix_time(1_000_000_000, 0);
+    let mut reported = false;
+    let messages: Vec<_> = files
+        .iter()
+        .map(|file| touch_one(file, time, time, flags).unwrap_err())
+        .map(|err| error_message(&err, flags, &mut reported))
+        .collect();
+    assert_eq!(
+        vec![Some("updating symlink times is not supported on this platform".to_owned()), None],
+        messages
+    );
+
+    // Without `-h`, the times are set as usual
+    let matches = cli::create_app().get_matches_from(vec!["touch", "file62.rs"]);
+    let mut flags = TouchFlags::from_matches(&matches).unwrap();
+    flags.symlink_times = &Unsupported;
+    assert_eq!(Touched::Updated, touch_one("file62.rs", time, time, flags).unwrap());
+    let file_metadata = metadata("file62.rs").unwrap();
+    assert_eq!(FileTime::from_last_modification_time(&file_metadata), time);
+
+    remove_test_files(&files).unwrap();
+}
+
+#[test]
+fn touch_create_file_no_follow() {
+    use std::os::unix::fs::symlink;
+
+    symlink("file36.rs", "file35.rs").unwrap();
+
+    assert!(create_file("file35.rs", true).is_err());
+    assert!(metadata("file36.rs").is_err());
+
+    remove_test_files(&["file35.rs"]).unwrap();
+}
+
+#[test]
+fn touch_create_file_mode_with_umask() {
+    use std::os::unix::fs::PermissionsExt;
+
+    use coreutils_core::libc::umask;
+
+    let old_umask = unsafe { umask(0o027) };
+    let created = create_file("file60.rs", false);
+    unsafe { umask(old_umask) };
+    created.unwrap();
+
+    assert_eq!(metadata("file60.rs").unwrap().permissions().mode() & 0o777, 0o640);
+
+    remove_test_files(&["file60.rs"]).unwrap();
+}
+
+#[test]
+fn touch_create_file_does_not_truncate() {
+    use std::io::Write;
+
+    File::create("file37.rs").unwrap().write_all(b"content").unwrap();
+
+    create_file("file37.rs", false).unwrap();
+
+    assert_eq!(metadata("file37.rs").unwrap().len(), 7);
+
+    remove_test_files(&["file37.rs"]).unwrap();
+}
+
+#[test]
+fn touch_parse_files0() {
+    let expected = vec!["file38.rs", "file39.rs"];
+
+    assert_eq!(parse_files0(&b"file38.rs\0file39.rs\0"[..]).unwrap(), expected);
+    assert_eq!(parse_files0(&b"file38.rs\0file39.rs"[..]).unwrap(), expected);
+    assert_eq!(parse_files0(&b"file with spaces\nand newline"[..]).unwrap(), vec![
+        "file with spaces\nand newline"
+    ]);
+    assert!(parse_files0(&b""[..]).unwrap().is_empty());
+    assert!(parse_files0(&b"\0"[..]).unwrap().is_empty());
+
+    assert!(parse_files0(&b"file38.rs\0\0file39.rs"[..]).is_err());
+    assert!(parse_files0(&b"\xff\0"[..]).is_err());
+}
+
+#[test]
+fn touch_files0_from() {
+    use std::io::Write;
+
+    File::create("file40.rs").unwrap().write_all(b"file41.rs\0file42.rs\0").unwrap();
+
+    let matches = cli::create_app().get_matches_from(vec!["touch", "--files0-from", "file40.rs"]);
+    let files0 = read_files0_from(matches.value_of("files0_from").unwrap()).unwrap();
+
+    assert_eq!(files0, vec!["file41.rs", "file42.rs"]);
+    assert!(read_files0_from("file43.rs").is_err());
+
+    remove_test_files(&["file40.rs"]).unwrap();
+}
+
+#[test]
+fn touch_files0_from_conflicts_with_files() {
+    let res =
+        cli::create_app().get_matches_from_safe(vec!["touch", "--files0-from", "-", "file44.rs"]);
+
+    assert!(res.is_err());
+}
+
+#[test]
+fn touch_many_files_with_jobs() {
+    let dir = TempDir::new("touch-jobs").unwrap();
+    let paths: Vec<_> =
+        (0..200).map(|n| dir.join(format!("file{}", n)).to_str().unwrap().to_owned()).collect();
+
+    let mut args = vec!["touch", "-j", "4"];
+    args.extend(paths.iter().map(String::as_str));
+    // A failure in the middle does not stop the other jobs
+    args.insert(100, "missing_dir/file45.rs");
+    let matches = cli::create_app().get_matches_from(args);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    assert_eq!(touch(&files, flags, &mut io::sink()), 1);
+    assert!(paths.iter().all(|path| metadata(path).is_ok()));
+}
+
+#[test]
+fn touch_more_jobs_than_files() {
+    let matches =
+        cli::create_app().get_matches_from(vec!["touch", "--jobs=8", "file46.rs", "file47.rs"]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    assert_eq!(touch(&files, flags, &mut io::sink()), 0);
+    assert!(metadata("file46.rs").is_ok() && metadata("file47.rs").is_ok());
+
+    remove_test_files(&files).unwrap();
+}
+
+#[test]
+fn touch_invalid_jobs() {
+    for jobs in &["0", "1.5", "four"] {
+        let matches = cli::create_app().get_matches_from(vec!["touch", "-j", jobs, "file48.rs"]);
+
+        let err = TouchFlags::from_matches(&matches).unwrap_err();
+        assert_eq!(format!("invalid number of jobs: '{}'", jobs), err);
+    }
+}
+
+#[test]
+fn touch_verbose() {
+    File::create("file49.rs").unwrap();
+
+    let args = vec!["touch", "-v", "file49.rs", "file50.rs", "missing_dir/file51.rs"];
+    let matches = cli::create_app().get_matches_from(args);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+    let mut out = Vec::new();
+
+    assert_eq!(touch(&files, flags, &mut out), 1);
+    // One line for each file acted upon, none for the file that failed
+    assert_eq!(
+        "touch: setting times of 'file49.rs'\ntouch: created 'file50.rs'\n",
+        String::from_utf8(out).unwrap()
+    );
+
+    remove_test_files(&["file49.rs", "file50.rs"]).unwrap();
+}
+
+#[test]
+fn touch_verbose_no_create() {
+    let matches = cli::create_app().get_matches_from(vec!["touch", "-v", "-c", "file52.rs"]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+    let mut out = Vec::new();
+
+    assert_eq!(touch(&files, flags, &mut out), 0);
+    assert!(out.is_empty());
+    assert!(metadata("file52.rs").is_err());
+}
+
+#[test]
+fn touch_not_verbose() {
+    let matches = cli::create_app().get_matches_from(vec!["touch", "file53.rs"]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+    let mut out = Vec::new();
+
+    assert_eq!(touch(&files, flags, &mut out), 0);
+    assert!(out.is_empty());
+
+    remove_test_files(&files).unwrap();
+}
+
+#[test]
+fn touch_files_named_like_flags() {
+    let matches =
+        cli::create_app().get_matches_from(vec!["touch", "--", "--weird-name", "-d", "-c"]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    // Everything after `--` is a file, not a flag
+    assert_eq!(vec!["--weird-name", "-d", "-c"], files);
+    assert!(flags.date.is_none() && !flags.no_create);
+
+    assert_eq!(touch(&files, flags, &mut io::sink()), 0);
+    assert!(files.iter().all(|file| metadata(file).is_ok()));
+
+    remove_test_files(&files).unwrap();
+}
+
+#[test]
+fn touch_missing_path_with_trailing_slash() {
+    let matches = cli::create_app().get_matches_from(vec!["touch", "dir54/"]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+    let time = FileTime::from_unix_time(0, 0);
+
+    let err = touch_one("dir54/", time, time, flags).unwrap_err();
+    assert_eq!(io::ErrorKind::NotFound, err.kind());
+    assert!(err.to_string().starts_with("cannot touch 'dir54/': "));
+    assert!(metadata("dir54").is_err());
+
+    // Neither a regular file is taken as a directory
+    File::create("file55.rs").unwrap();
+    assert!(touch_one("file55.rs/", time, time, flags).is_err());
+
+    assert_eq!(touch(&["dir54/"], flags, &mut io::sink()), 1);
+    remove_test_files(&["file55.rs"]).unwrap();
+}
+
+#[test]
+fn touch_directory_with_trailing_slash() {
+    let dir = TempDir::new("touch-trailing-slash").unwrap();
+    let arg = format!("{}/", dir.path().display());
+
+    let matches = cli::create_app().get_matches_from(vec!["touch", "-d", "@1000000000", &arg]);
+
+    let flags = TouchFlags::from_matches(&matches).unwrap();
+    let files: Vec<_> = matches.values_of("FILE").unwrap().collect();
+
+    assert_eq!(touch(&files, flags, &mut io::sink()), 0);
+
+    let mtime = FileTime::from_last_modification_time(&metadata(dir.path()).unwrap());
+    assert_eq!(FileTime::from_unix_time(1_000_000_000, 0), mtime);
+}
+
+#[test]
+fn bash_completion_names_touch() {
+    let mut completion = Vec::new();
+    cli::create_app().gen_completions_to("touch", clap::Shell::Bash, &mut completion);
+
+    let completion = String::from_utf8(completion).unwrap();
+    assert!(completion.contains("touch"));
+    assert!(completion.contains("--files0-from"));
+}